@@ -1,24 +1,191 @@
 use miette::IntoDiagnostic;
 use rattler_digest::{HashingReader, Sha256, Sha256Hash};
 use std::io::{BufReader, Read};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use tokio::io::AsyncRead;
 use tokio_util::io::SyncIoBridge;
 use zip::read::read_zipfile_from_stream;
 
+/// The signature of a zip central directory file header
+const CENTRAL_DIRECTORY_SIGNATURE: [u8; 4] = *b"PK\x01\x02";
+
+/// The file type bits of a unix mode
+const S_IFMT: u32 = 0o170000;
+
+/// The file type bits of a directory
+#[cfg(unix)]
+const S_IFDIR: u32 = 0o040000;
+
+/// The file type bits of a symlink
+const S_IFLNK: u32 = 0o120000;
+
+/// The magic bytes of a zip archive
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// The magic bytes of a gzip stream
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// The magic bytes of an xz stream
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// The magic bytes of a zstd frame
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// The supported archive formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// Zip archive
+    Zip,
+
+    /// Gzip-compressed tarball
+    TarGz,
+
+    /// Xz-compressed tarball
+    TarXz,
+
+    /// Zstd-compressed tarball
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// Detect the archive format from the leading bytes of an archive
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        [
+            (ZIP_MAGIC, ArchiveFormat::Zip),
+            (GZIP_MAGIC, ArchiveFormat::TarGz),
+            (XZ_MAGIC, ArchiveFormat::TarXz),
+            (ZSTD_MAGIC, ArchiveFormat::TarZst),
+        ]
+        .into_iter()
+        .find_map(|(magic, format)| bytes.starts_with(magic).then_some(format))
+    }
+
+    /// Detect the archive format of the file at the given `path`
+    pub fn detect(path: &Path) -> std::io::Result<Option<Self>> {
+        let mut bytes = Vec::with_capacity(XZ_MAGIC.len());
+        std::fs::File::open(path)?
+            .take(XZ_MAGIC.len() as u64)
+            .read_to_end(&mut bytes)?;
+
+        Ok(Self::from_magic(&bytes))
+    }
+}
+
+impl std::str::FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zip" => Ok(ArchiveFormat::Zip),
+            "tar.gz" | "tgz" => Ok(ArchiveFormat::TarGz),
+            "tar.xz" | "txz" => Ok(ArchiveFormat::TarXz),
+            "tar.zst" | "tzst" => Ok(ArchiveFormat::TarZst),
+            _ => Err(format!("unsupported archive format '{s}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveFormat::Zip => write!(f, "zip"),
+            ArchiveFormat::TarGz => write!(f, "tar.gz"),
+            ArchiveFormat::TarXz => write!(f, "tar.xz"),
+            ArchiveFormat::TarZst => write!(f, "tar.zst"),
+        }
+    }
+}
+
+/// Extract an archive of the given format from the `reader` into `destination`
+///
+/// # Returns
+///
+/// The SHA256 checksum of the archive
+pub async fn extract(
+    reader: impl AsyncRead + Send + 'static,
+    format: ArchiveFormat,
+    destination: &Path,
+) -> miette::Result<Sha256Hash> {
+    let reader = SyncIoBridge::new(Box::pin(reader));
+
+    let destination = destination.to_owned();
+    let result = tokio::task::spawn_blocking(move || match format {
+        ArchiveFormat::Zip => extract_zip_sync(reader, &destination),
+        _ => extract_tar_sync(reader, format, &destination),
+    })
+    .await;
+
+    match result {
+        Ok(result) => result,
+        Err(err) => Err(err).into_diagnostic(),
+    }
+}
+
 #[inline(always)]
 pub fn stream_tar_gz(reader: impl Read) -> tar::Archive<impl Read + Sized> {
     tar::Archive::new(flate2::read::GzDecoder::new(reader))
 }
 
-fn extract_tar_gz_sync(reader: impl Read, destination: &Path) -> miette::Result<Sha256Hash> {
+fn extract_tar_sync(
+    reader: impl Read,
+    format: ArchiveFormat,
+    destination: &Path,
+) -> miette::Result<Sha256Hash> {
     std::fs::create_dir_all(destination).into_diagnostic()?;
 
     let mut reader = HashingReader::<_, Sha256>::new(BufReader::new(reader));
+    let mut rejected = vec![];
 
-    stream_tar_gz(&mut reader)
-        .unpack(destination)
-        .into_diagnostic()?;
+    let decoder: Box<dyn Read> = match format {
+        ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(&mut reader)),
+        ArchiveFormat::TarXz => Box::new(lzma_rust2::XzReader::new(&mut reader, true)),
+        ArchiveFormat::TarZst => Box::new(zstd::Decoder::new(&mut reader).into_diagnostic()?),
+        ArchiveFormat::Zip => unreachable!("zip is not a tar archive"),
+    };
+
+    for entry in tar::Archive::new(decoder).entries().into_diagnostic()? {
+        let mut entry = entry.into_diagnostic()?;
+        let path = entry.path().into_diagnostic()?.into_owned();
+
+        let relative = match check_entry_path(&path)
+            .and_then(|relative| check_entry_parent(destination, &relative).map(|_| relative))
+        {
+            Ok(relative) => relative,
+            Err(reason) => {
+                rejected.push(format!("{}: {}", path.display(), reason));
+                continue;
+            }
+        };
+
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            let target = entry
+                .link_name()
+                .into_diagnostic()?
+                .map(|t| t.into_owned())
+                .unwrap_or_default();
+            // hard links are relative to the archive root, symlinks to the
+            // directory of the entry
+            let base = match entry_type.is_hard_link() {
+                true => Path::new(""),
+                false => relative.parent().unwrap_or(Path::new("")),
+            };
+            if !is_link_enclosed(destination, base, &target) {
+                rejected.push(format!(
+                    "{} -> {}: link points outside the destination",
+                    path.display(),
+                    target.display()
+                ));
+                continue;
+            }
+        }
+
+        entry.unpack_in(destination).into_diagnostic()?;
+    }
+
+    if !rejected.is_empty() {
+        return Err(unsafe_entries_error(rejected));
+    }
 
     // sink the rest of the data, calculating the hash
     std::io::copy(&mut reader, &mut std::io::sink()).into_diagnostic()?;
@@ -32,51 +199,258 @@ pub async fn extract_tar_gz(
     reader: impl AsyncRead + Send + 'static,
     destination: &Path,
 ) -> miette::Result<Sha256Hash> {
-    let reader = SyncIoBridge::new(Box::pin(reader));
+    extract(reader, ArchiveFormat::TarGz, destination).await
+}
 
-    let destination = destination.to_owned();
-    match tokio::task::spawn_blocking(move || extract_tar_gz_sync(reader, &destination)).await {
-        Ok(result) => result,
-        Err(err) => Err(err).into_diagnostic(),
-    }
+pub async fn extract_tar_xz(
+    reader: impl AsyncRead + Send + 'static,
+    destination: &Path,
+) -> miette::Result<Sha256Hash> {
+    extract(reader, ArchiveFormat::TarXz, destination).await
+}
+
+pub async fn extract_tar_zst(
+    reader: impl AsyncRead + Send + 'static,
+    destination: &Path,
+) -> miette::Result<Sha256Hash> {
+    extract(reader, ArchiveFormat::TarZst, destination).await
 }
 
 fn extract_zip_sync(reader: impl Read, destination: &Path) -> miette::Result<Sha256Hash> {
     std::fs::create_dir_all(destination).into_diagnostic()?;
 
     let mut reader = HashingReader::<_, Sha256>::new(BufReader::new(reader));
+    let mut rejected = vec![];
 
-    while let Some(file) = read_zipfile_from_stream(&mut reader).into_diagnostic()? {
-        let path = file.mangled_name();
-        let path = destination.join(path);
+    while let Some(mut file) = read_zipfile_from_stream(&mut reader).into_diagnostic()? {
+        let Some(relative) = zip_entry_path(file.name(), &mut rejected) else {
+            continue;
+        };
+        if let Err(reason) = check_entry_parent(destination, &relative) {
+            rejected.push(format!("{}: {}", file.name(), reason));
+            continue;
+        }
+        let path = destination.join(relative);
 
         if file.is_dir() {
             std::fs::create_dir_all(&path).into_diagnostic()?;
         } else {
             std::fs::create_dir_all(path.parent().unwrap()).into_diagnostic()?;
-            let mut file = file;
+            // replace instead of writing through an existing file, which may
+            // be a symlink
+            remove_file_if_exists(&path).into_diagnostic()?;
             let mut dest = std::fs::File::create(&path).into_diagnostic()?;
             std::io::copy(&mut file, &mut dest).into_diagnostic()?;
         }
     }
 
-    // sink the rest of the data, calculating the hash
-    std::io::copy(&mut reader, &mut std::io::sink()).into_diagnostic()?;
+    // The unix mode of the entries, which also tells whether an entry is a
+    // symlink, is only recorded in the central directory at the end of the
+    // stream. Its signature has been consumed by the last read above, reading
+    // the rest of the data also completes the hash.
+    let mut central_directory = CENTRAL_DIRECTORY_SIGNATURE.to_vec();
+    reader
+        .read_to_end(&mut central_directory)
+        .into_diagnostic()?;
+
+    for (name, mode) in central_directory_modes(&central_directory) {
+        // rejected entries have been reported already
+        let Some(relative) = zip_entry_path(&name, &mut vec![]) else {
+            continue;
+        };
+        let path = destination.join(&relative);
+
+        if mode & S_IFMT == S_IFLNK {
+            // the content of a symlink entry is the link target
+            let target = std::fs::read_to_string(&path).into_diagnostic()?;
+            if let Err(reason) = check_entry_parent(destination, &relative) {
+                rejected.push(format!("{}: {}", name, reason));
+                continue;
+            }
+            let base = relative.parent().unwrap_or(Path::new(""));
+            if !is_link_enclosed(destination, base, Path::new(&target)) {
+                rejected.push(format!(
+                    "{} -> {}: link points outside the destination",
+                    name, target
+                ));
+                std::fs::remove_file(&path).into_diagnostic()?;
+                continue;
+            }
+
+            create_symlink(&target, &path).into_diagnostic()?;
+            continue;
+        }
+
+        #[cfg(unix)]
+        if mode & S_IFMT != S_IFDIR {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode & 0o777))
+                .into_diagnostic()?;
+        }
+    }
+
+    if !rejected.is_empty() {
+        return Err(unsafe_entries_error(rejected));
+    }
 
     let (_, sha256) = reader.finalize();
 
     Ok(sha256)
 }
 
+/// Get the relative extraction path of the zip entry with the given name, or
+/// record the entry as rejected
+fn zip_entry_path(name: &str, rejected: &mut Vec<String>) -> Option<PathBuf> {
+    // zip entries created on Windows may use `\\` as the separator
+    let name = name.replace('\\', "/");
+    match check_entry_path(Path::new(&name)) {
+        Ok(relative) => Some(relative),
+        Err(reason) => {
+            rejected.push(format!("{}: {}", name, reason));
+            None
+        }
+    }
+}
+
+/// Read the names and unix modes of the entries from the central directory
+/// of a zip archive
+///
+/// Entries without a unix mode are skipped.
+fn central_directory_modes(data: &[u8]) -> Vec<(String, u32)> {
+    let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]) as usize;
+
+    let mut entries = vec![];
+    let mut offset = 0;
+    while data.len() >= offset + 46 && data[offset..offset + 4] == CENTRAL_DIRECTORY_SIGNATURE {
+        let name_len = u16_at(offset + 28);
+        let extra_len = u16_at(offset + 30);
+        let comment_len = u16_at(offset + 32);
+        let external_attributes = u32::from_le_bytes(
+            data[offset + 38..offset + 42]
+                .try_into()
+                .expect("should be 4 bytes"),
+        );
+
+        let name_end = offset + 46 + name_len;
+        let Some(name) = data.get(offset + 46..name_end) else {
+            break;
+        };
+
+        // the high 16 bits of the external attributes hold the unix mode
+        let mode = external_attributes >> 16;
+        if mode != 0 {
+            entries.push((String::from_utf8_lossy(name).into_owned(), mode));
+        }
+
+        offset = name_end + extra_len + comment_len;
+    }
+
+    entries
+}
+
 pub async fn extract_zip(
     reader: impl AsyncRead + Send + 'static,
     destination: &Path,
 ) -> miette::Result<Sha256Hash> {
-    let reader = SyncIoBridge::new(Box::pin(reader));
+    extract(reader, ArchiveFormat::Zip, destination).await
+}
 
-    let destination = destination.to_owned();
-    match tokio::task::spawn_blocking(move || extract_zip_sync(reader, &destination)).await {
-        Ok(result) => result,
-        Err(err) => Err(err).into_diagnostic(),
+/// Check that the path of an archive entry stays within the destination
+///
+/// # Returns
+///
+/// The normalized relative path of the entry, or the reason why the entry is
+/// rejected
+fn check_entry_path(path: &Path) -> Result<PathBuf, &'static str> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => return Err("absolute path"),
+            Component::ParentDir => return Err("path contains `..`"),
+            Component::CurDir => {}
+            Component::Normal(c) => relative.push(c),
+        }
     }
+
+    Ok(relative)
+}
+
+/// Check that the parent directories of an archive entry, relative to the
+/// `destination`, are not symlinks
+///
+/// Links are checked against the paths within the archive, an entry written
+/// through a symlink extracted earlier could end up anywhere.
+fn check_entry_parent(destination: &Path, relative: &Path) -> Result<(), &'static str> {
+    let parent = relative.parent().unwrap_or(Path::new(""));
+    match parent
+        .ancestors()
+        .any(|dir| !dir.as_os_str().is_empty() && is_symlink(&destination.join(dir)))
+    {
+        true => Err("path passes through a symlink"),
+        false => Ok(()),
+    }
+}
+
+/// Check that the link `target`, relative to the `base` directory within the
+/// `destination`, points inside the destination
+///
+/// Links resolving through another symlink are rejected, their target cannot
+/// be checked from the paths alone.
+fn is_link_enclosed(destination: &Path, base: &Path, target: &Path) -> bool {
+    let mut resolved = base
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect::<PathBuf>();
+    let mut through_symlink = false;
+
+    for component in target.components() {
+        if through_symlink && component != Component::CurDir {
+            return false;
+        }
+        match component {
+            Component::Prefix(_) | Component::RootDir => return false,
+            Component::ParentDir if !resolved.pop() => return false,
+            Component::ParentDir | Component::CurDir => {}
+            Component::Normal(c) => {
+                resolved.push(c);
+                through_symlink = is_symlink(&destination.join(&resolved));
+            }
+        }
+    }
+
+    true
+}
+
+fn is_symlink(path: &Path) -> bool {
+    std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+fn unsafe_entries_error(rejected: Vec<String>) -> miette::Report {
+    miette::miette!(
+        help = "the archive may be corrupted or tampered with, please report it",
+        "refusing to extract {} unsafe archive entries:\n  {}",
+        rejected.len(),
+        rejected.join("\n  ")
+    )
+}
+
+fn remove_file_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Replace the file at `path`, holding the link target, with a symlink
+#[cfg(unix)]
+fn create_symlink(target: &str, path: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(path)?;
+    std::os::unix::fs::symlink(target, path)
+}
+
+// Creating symlinks requires extra privileges on Windows, the link target is
+// kept as a regular file like other zip tools do
+#[cfg(not(unix))]
+fn create_symlink(_target: &str, _path: &Path) -> std::io::Result<()> {
+    Ok(())
 }
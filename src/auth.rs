@@ -0,0 +1,205 @@
+use reqwest_middleware::{ClientWithMiddleware, RequestBuilder};
+use std::path::PathBuf;
+use url::Url;
+
+use crate::constant::{ENVNAME_MOONUP_DIST_SERVER_TOKEN, ENVNAME_NETRC};
+use crate::settings::{HostAuth, Settings};
+
+/// Credentials of a request
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) enum Credentials {
+    Bearer(String),
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+}
+
+/// Build a GET request carrying the credentials of the URL's host
+///
+/// Credentials are looked up in this order:
+///
+/// 1. the `user:password@` userinfo of the URL itself
+/// 2. `MOONUP_DIST_SERVER_TOKEN`, for the host of the distribution server
+/// 3. the `[auth."<host>"]` settings
+/// 4. the `NETRC` file, `~/.netrc` by default
+pub(crate) fn get(client: &ClientWithMiddleware, url: Url) -> miette::Result<RequestBuilder> {
+    let credentials = match url.username().is_empty() && url.password().is_none() {
+        true => credentials(&url)?,
+        // Userinfo is sent as basic auth by reqwest
+        false => None,
+    };
+
+    let request = client.get(url);
+    Ok(match credentials {
+        Some(Credentials::Bearer(token)) => request.bearer_auth(token),
+        Some(Credentials::Basic { username, password }) => request.basic_auth(username, password),
+        None => request,
+    })
+}
+
+/// Look up the credentials of the URL's host
+fn credentials(url: &Url) -> miette::Result<Option<Credentials>> {
+    let Some(host) = url.host_str() else {
+        return Ok(None);
+    };
+    let port = url.port_or_known_default();
+
+    if let Ok(token) = std::env::var(ENVNAME_MOONUP_DIST_SERVER_TOKEN)
+        && !token.is_empty()
+        && Url::parse(&crate::utils::dist_server_baseurl())
+            .is_ok_and(|d| d.host_str() == Some(host) && d.port_or_known_default() == port)
+    {
+        tracing::trace!("using {} for {}", ENVNAME_MOONUP_DIST_SERVER_TOKEN, host);
+        return Ok(Some(Credentials::Bearer(token)));
+    }
+
+    let settings = Settings::load()?;
+    let host_port = port.map(|port| format!("{host}:{port}"));
+    let auth = host_port
+        .iter()
+        .map(String::as_str)
+        .chain([host])
+        .find_map(|key| {
+            settings
+                .auth
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        });
+    if let Some((key, auth)) = auth {
+        tracing::trace!("using [auth.\"{}\"] settings for {}", key, host);
+        return Ok(host_auth(auth));
+    }
+
+    Ok(netrc_credentials(host))
+}
+
+fn host_auth(auth: &HostAuth) -> Option<Credentials> {
+    match (&auth.token, &auth.username) {
+        (Some(token), _) => Some(Credentials::Bearer(token.clone())),
+        (None, Some(username)) => Some(Credentials::Basic {
+            username: username.clone(),
+            password: auth.password.clone(),
+        }),
+        (None, None) => None,
+    }
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    match std::env::var_os(ENVNAME_NETRC) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => {
+            let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+            dirs::home_dir().map(|home| home.join(name))
+        }
+    }
+}
+
+fn netrc_credentials(host: &str) -> Option<Credentials> {
+    let path = netrc_path()?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::warn!("failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    let credentials = parse_netrc(&content, host);
+    if credentials.is_some() {
+        tracing::trace!("using {} for {}", path.display(), host);
+    }
+    credentials
+}
+
+#[derive(Default)]
+struct NetrcEntry {
+    /// `None` for the `default` entry
+    machine: Option<String>,
+    login: Option<String>,
+    password: Option<String>,
+}
+
+/// Find the credentials of the host in a netrc file
+///
+/// The entry of the host is preferred over the `default` entry, `account`
+/// tokens and `macdef` macros are ignored.
+fn parse_netrc(content: &str, host: &str) -> Option<Credentials> {
+    let mut entries: Vec<NetrcEntry> = vec![];
+    let mut key = None;
+    let mut in_macdef = false;
+
+    for line in content.lines() {
+        // Macros last until the next blank line
+        if in_macdef {
+            in_macdef = !line.trim().is_empty();
+            continue;
+        }
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        for token in line.split_whitespace() {
+            match key.take() {
+                Some("machine") => entries.push(NetrcEntry {
+                    machine: Some(token.to_owned()),
+                    ..Default::default()
+                }),
+                Some("login") => {
+                    if let Some(entry) = entries.last_mut() {
+                        entry.login = Some(token.to_owned());
+                    }
+                }
+                Some("password") => {
+                    if let Some(entry) = entries.last_mut() {
+                        entry.password = Some(token.to_owned());
+                    }
+                }
+                Some(_) => {}
+                None if token == "default" => entries.push(NetrcEntry::default()),
+                None => {
+                    in_macdef = token == "macdef";
+                    key = Some(token);
+                }
+            }
+        }
+    }
+
+    let entry = entries
+        .iter()
+        .find(|e| {
+            e.machine
+                .as_deref()
+                .is_some_and(|m| m.eq_ignore_ascii_case(host))
+        })
+        .or_else(|| entries.iter().find(|e| e.machine.is_none()))?;
+
+    Some(Credentials::Basic {
+        username: entry.login.clone()?,
+        password: entry.password.clone(),
+    })
+}
+
+/// Redact the userinfo of a URL for logging and persisting
+pub(crate) fn redact_url(url: &Url) -> String {
+    let mut url = url.clone();
+    if !url.username().is_empty() {
+        let _ = url.set_username("***");
+    }
+    if url.password().is_some() {
+        let _ = url.set_password(Some("***"));
+    }
+    url.to_string()
+}
+
+/// Redact the userinfo of a URL string, the string is kept as is if it has
+/// no userinfo or is not a valid URL
+pub(crate) fn redact(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) if !parsed.username().is_empty() || parsed.password().is_some() => {
+            redact_url(&parsed)
+        }
+        _ => url.to_owned(),
+    }
+}
@@ -1,13 +1,21 @@
 use anyhow::Result;
 use std::env;
 use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
-use std::process::{Command, ExitStatus};
+use std::process::{Command, ExitStatus, Stdio};
 
-use moonup::constant::RECURSION_LIMIT;
+use moonup::constant::{RECURSION_LIMIT, SHIM_LOG_FILE};
+use moonup::settings::AutoInstall;
 use moonup::toolchain::ToolchainSpec;
-use moonup::toolchain::resolve::detect_active_toolchainspec;
-use moonup::{moonup_home, runner};
+use moonup::toolchain::resolve::{ToolchainSource, detect_active_toolchainspec_with_source};
+use moonup::{moonup_home, runner, shim};
+
+/// The version marker read by moonup to detect shims poured by another
+/// version, must match `constant::SHIM_VERSION_MARKER`
+#[used]
+static VERSION_MARKER: &str = concat!("moonup-shim-version:", env!("CARGO_PKG_VERSION"), "\0");
 
 pub fn main() {
     match run() {
@@ -15,9 +23,7 @@ pub fn main() {
             eprintln!("Error: {err}");
             std::process::exit(1);
         }
-        Ok(status) => {
-            std::process::exit(status.code().unwrap_or(1));
-        }
+        Ok(status) => runner::exit_with(status),
     }
 }
 
@@ -26,9 +32,17 @@ fn run() -> Result<ExitStatus> {
     let recursion_count = recursion_guard()?;
 
     let args = env::args_os().collect::<Vec<_>>();
-    let current_exe = env::current_exe()
-        .unwrap_or_else(|_| PathBuf::from(&args[0]))
-        .with_extension(""); // ensure `.exe` is removed
+    let mut current_exe = env::current_exe().unwrap_or_else(|_| PathBuf::from(&args[0]));
+    // Symlinked shims resolve to `moonup-shim`, dispatch on the name they
+    // were invoked by instead
+    if current_exe
+        .file_stem()
+        .is_some_and(|stem| stem == "moonup-shim")
+        && let Some(arg0) = args.first()
+    {
+        current_exe = PathBuf::from(arg0);
+    }
+    let current_exe = current_exe.with_extension(""); // ensure `.exe` is removed
     let current_exe_name = current_exe
         .file_name()
         .and_then(|name| name.to_str())
@@ -49,51 +63,43 @@ fn run() -> Result<ExitStatus> {
     // - If the first argument is a toolchain spec, use it.
     // - If the `MOONUP_TOOLCHAIN_SPEC` environment variable is set, use it.
     // - Otherwise, detect the active toolchain.
-    let active_toolchain = if args_1_is_toolchain {
+    let (active_toolchain, source) = if args_1_is_toolchain {
         let version = args_1.expect("has arg version").strip_prefix('+').unwrap();
-        version.to_string()
+        (version.to_string(), ToolchainSource::Argument)
     } else if let Some(toolchain_spec) = env::var_os("MOONUP_TOOLCHAIN_SPEC") {
-        toolchain_spec
+        let version = toolchain_spec
             .to_str()
             .expect("MOONUP_TOOLCHAIN_SPEC should be valid UTF-8")
-            .to_string()
+            .to_string();
+        (version, ToolchainSource::Environment)
     } else {
-        detect_active_toolchainspec()
+        detect_active_toolchainspec_with_source()
     };
 
     let active_toolchain_root = moonup_home().join("toolchains").join(&active_toolchain);
 
     // If the active toolchain is not installed, call `moonup install`
-    // to install it.
+    // to install it, as allowed by the auto-install policy.
     if !active_toolchain_root.exists() {
         let version = active_toolchain_root
             .file_name()
             .and_then(|v| v.to_str())
             .expect("should get active toolchain version");
 
-        println!("toolchain version '{version}' not installed");
-
-        let mut cmd = Command::new("moonup");
-        cmd.args(["install", version]);
-
-        match cmd.status() {
-            Err(e) => return Err(anyhow::anyhow!("Failed to run moonup install: {}", e)),
-            Ok(status) if !status.success() => {
-                return Err(anyhow::anyhow!("Failed to install active toolchain"));
-            }
-            Ok(_) => {}
-        }
+        check_auto_install(version, &source)?;
+        auto_install(version, &source, current_exe_name == "moon-lsp")?;
     }
 
     if current_exe_name == "moon" {
         // intercept `moon upgrade` and proxy it to `moonup upgrade`
         if args.len() > 1 && args[1] == "upgrade" {
-            let mut cmd = Command::new("moonup");
+            let moonup = shim::locate_moonup();
+            let mut cmd = Command::new(&moonup);
             cmd.args(["update"]);
 
             return cmd
                 .status()
-                .map_err(|e| anyhow::anyhow!("Failed to run moonup upgrade: {}", e));
+                .map_err(|e| anyhow::anyhow!("Failed to run {} update: {}", moonup.display(), e));
         }
     }
 
@@ -106,7 +112,105 @@ fn run() -> Result<ExitStatus> {
     let mut cmd = runner::build_command(spec, run_args)?;
     cmd.env("MOONUP_RECURSION_COUNT", (recursion_count + 1).to_string());
 
-    cmd.status().map_err(anyhow::Error::from)
+    runner::exec(cmd)
+}
+
+/// Check that the auto-install policy allows installing the missing toolchain
+fn check_auto_install(version: &str, source: &ToolchainSource) -> Result<()> {
+    let (policy, policy_source) = shim::auto_install_policy()?;
+    let refuse = |reason: &str| {
+        Err(anyhow::anyhow!(
+            "toolchain '{version}' is not installed (requested by {source})\n\
+             auto-install is '{policy}' as set by {policy_source}{reason}, \
+             run `moonup install {version}` to install it"
+        ))
+    };
+
+    match policy {
+        AutoInstall::Always => Ok(()),
+        AutoInstall::Never => refuse(""),
+        AutoInstall::Prompt => {
+            if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+                return refuse(" but there is no terminal to prompt on");
+            }
+
+            let confirmed = dialoguer::Confirm::new()
+                .with_prompt(format!(
+                    "Toolchain '{version}' requested by {source} is not installed. Install it now?"
+                ))
+                .default(true)
+                .interact()
+                .map_err(|e| anyhow::anyhow!("Failed to prompt for auto-install: {}", e))?;
+            match confirmed {
+                true => Ok(()),
+                false => refuse(" and declined"),
+            }
+        }
+    }
+}
+
+/// Install the missing toolchain by `moonup install`
+///
+/// stdout belongs to the proxied command, e.g. the protocol of `moon-lsp`
+/// or output parsed by tools, so nothing is written to it. The output goes
+/// to stderr if stdout is a terminal, otherwise to the shim log file. For
+/// the language server, only the log file is written to.
+fn auto_install(version: &str, source: &ToolchainSource, is_lsp: bool) -> Result<()> {
+    let log = match is_lsp || !std::io::stdout().is_terminal() {
+        true => open_log(),
+        false => None,
+    };
+
+    let moonup = shim::locate_moonup();
+    let mut cmd = Command::new(&moonup);
+    cmd.args(["install", version]).stdin(Stdio::null());
+
+    let message = format!("toolchain version '{version}' not installed, requested by {source}");
+    match &log {
+        Some((file, path)) => {
+            let _ = writeln!(
+                &*file,
+                "[{}] {message}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+            );
+            if !is_lsp {
+                eprintln!("{message}, installing (see {})", path.display());
+            }
+            cmd.stdout(file.try_clone()?).stderr(file.try_clone()?);
+        }
+        None => {
+            eprintln!("{message}");
+            cmd.stdout(std::io::stderr());
+        }
+    }
+
+    let see_log = match &log {
+        Some((_, path)) => format!(", see {}", path.display()),
+        None => String::new(),
+    };
+    match cmd.status() {
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to run {} install: {}",
+            moonup.display(),
+            e
+        )),
+        Ok(status) if !status.success() => Err(anyhow::anyhow!(
+            "Failed to install active toolchain '{version}'{see_log}"
+        )),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Open the shim log file for appending
+fn open_log() -> Option<(File, PathBuf)> {
+    let path = moonup_home().join("logs").join(SHIM_LOG_FILE);
+    std::fs::create_dir_all(path.parent()?).ok()?;
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .ok()?;
+    Some((file, path))
 }
 
 fn recursion_guard() -> Result<u8> {
@@ -0,0 +1,372 @@
+use chrono::{DateTime, Duration, Utc};
+use miette::{Context, IntoDiagnostic};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::dist_server::schema::{ChannelName, ComponentIndex, Release};
+use crate::toolchain::{InstalledToolchain, ToolchainSpec, installed_toolchains};
+
+/// Get the downloads directory
+///
+/// The downloads directory holds the cached release indexes and the
+/// content-addressed store of downloaded component archives.
+pub fn downloads_dir() -> PathBuf {
+    crate::moonup_home().join("downloads")
+}
+
+/// Get the content-addressed store directory
+///
+/// Component archives are stored by their SHA256 checksum, so the same
+/// archive referenced by several channels or tags is only stored once.
+pub fn store_dir() -> PathBuf {
+    let mut path = downloads_dir();
+    path.push("store");
+    path.push("sha256");
+    path
+}
+
+/// Get the path of the blob with the given SHA256 checksum in the store
+///
+/// # Returns
+///
+/// An error if the given checksum is not a valid hex-encoded SHA256 digest
+pub fn blob_path(sha256: &str) -> miette::Result<PathBuf> {
+    let is_valid = sha256.len() == 64 && sha256.bytes().all(|b| b.is_ascii_hexdigit());
+    if !is_valid {
+        return Err(miette::miette!("invalid sha256 checksum: '{}'", sha256));
+    }
+
+    Ok(store_dir().join(sha256.to_ascii_lowercase()))
+}
+
+/// Get the directory caching the component indexes of the given release
+pub fn release_dir(channel: &ChannelName, release: &Release) -> miette::Result<PathBuf> {
+    let mut path = downloads_dir();
+
+    match channel {
+        ChannelName::Bleeding => path.push("bleeding"),
+        ChannelName::Latest => {
+            path.push("latest");
+            path.push(&release.version);
+        }
+        ChannelName::Nightly => {
+            path.push("nightly");
+            path.push(
+                release
+                    .date
+                    .as_deref()
+                    .expect("nightly release should have build date"),
+            );
+        }
+        _ => return Err(miette::miette!("unsupported channel: {}", channel)),
+    }
+
+    Ok(path)
+}
+
+/// Collect the blobs referenced by the component indexes cached in `dir`
+///
+/// Only blobs that exist in the store are returned.
+pub fn referenced_blobs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(read_dir) = dir.read_dir() else {
+        return vec![];
+    };
+
+    read_dir
+        .filter_map(std::io::Result::ok)
+        .map(|e| e.path())
+        .filter(|p| is_json(p))
+        .flat_map(|p| referenced_blobs_in(&p))
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+/// The kind of a cache entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheEntryKind {
+    /// A downloaded component archive
+    Download,
+
+    /// A cached release index (`*.json` with its `*.lock` timestamp file)
+    Index,
+}
+
+/// An entry in the downloads directory
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// The path of the entry
+    pub path: PathBuf,
+
+    /// The kind of the entry
+    pub kind: CacheEntryKind,
+
+    /// The size of the entry in bytes, including its lockfile if any
+    pub size: u64,
+
+    /// The last modified time of the entry
+    pub modified: SystemTime,
+
+    /// The toolchains referencing the entry
+    pub toolchains: Vec<ToolchainSpec>,
+
+    /// Whether the entry is used by an installed toolchain
+    pub installed: bool,
+
+    /// Whether the entry is a stale index file, i.e. an expired or orphan
+    /// index file that would be fetched again anyway
+    pub stale: bool,
+}
+
+impl CacheEntry {
+    /// Get the path of the entry relative to the downloads directory
+    pub fn display_name(&self) -> String {
+        let downloads_dir = downloads_dir();
+        self.path
+            .strip_prefix(&downloads_dir)
+            .unwrap_or(&self.path)
+            .display()
+            .to_string()
+    }
+
+    /// Remove the entry, along with its lockfile if any
+    pub fn remove(&self) -> std::io::Result<()> {
+        if self.kind == CacheEntryKind::Index {
+            match std::fs::remove_file(lockfile_path(&self.path)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// List all entries in the downloads directory
+///
+/// Entries are sorted by their last modified time, oldest first.
+pub fn entries() -> miette::Result<Vec<CacheEntry>> {
+    let downloads_dir = downloads_dir();
+
+    let mut files = vec![];
+    collect_files(&downloads_dir, &mut files)
+        .into_diagnostic()
+        .wrap_err("failed to read downloads directory")?;
+
+    let installed = installed_toolchains()?;
+    let is_installed = |spec: &ToolchainSpec| installed.iter().any(|t| &t.name == spec);
+
+    // toolchains referencing each blob, derived from install receipts and
+    // cached component indexes
+    let mut blob_owners: HashMap<PathBuf, Vec<ToolchainSpec>> = HashMap::new();
+    let mut add_owners = |blob: PathBuf, specs: &[ToolchainSpec]| {
+        let owners = blob_owners.entry(blob).or_default();
+        for spec in specs.iter() {
+            if !owners.contains(spec) {
+                owners.push(spec.clone());
+            }
+        }
+    };
+
+    for toolchain in installed.iter() {
+        if let Some(receipt) = &toolchain.receipt {
+            for component in receipt.components.iter() {
+                if let Ok(blob) = blob_path(&component.sha256) {
+                    add_owners(blob, std::slice::from_ref(&toolchain.name));
+                }
+            }
+        }
+    }
+
+    for file in files.iter().filter(|f| is_json(f)) {
+        let specs = release_dir_toolchains(&downloads_dir, file, &installed);
+        for blob in referenced_blobs_in(file) {
+            add_owners(blob, &specs);
+        }
+    }
+
+    let mut entries = vec![];
+    for file in files.iter() {
+        let is_lockfile = file.extension().is_some_and(|ext| ext == "lock");
+        let (kind, lockfile) = if is_json(file) {
+            let lockfile = lockfile_path(file);
+            (
+                CacheEntryKind::Index,
+                lockfile.is_file().then_some(lockfile),
+            )
+        } else if is_lockfile {
+            // Lockfiles are accounted together with their index file,
+            // orphan lockfiles are listed on their own
+            let index_file = file.with_extension("");
+            if index_file.is_file() {
+                continue;
+            }
+            (CacheEntryKind::Index, None)
+        } else {
+            (CacheEntryKind::Download, None)
+        };
+
+        let Ok(metadata) = file.metadata() else {
+            continue;
+        };
+
+        let mut size = metadata.len();
+        if let Some(lockfile) = lockfile.as_deref() {
+            size += lockfile.metadata().map(|m| m.len()).unwrap_or(0);
+        }
+
+        let toolchains = match kind {
+            CacheEntryKind::Download if file.starts_with(store_dir()) => {
+                blob_owners.get(file).cloned().unwrap_or_default()
+            }
+            _ => release_dir_toolchains(&downloads_dir, file, &installed),
+        };
+
+        let stale = match kind {
+            CacheEntryKind::Index => match lockfile.as_deref() {
+                Some(lockfile) => is_index_expired(lockfile),
+                None => true,
+            },
+            CacheEntryKind::Download => false,
+        };
+
+        entries.push(CacheEntry {
+            installed: toolchains.iter().any(is_installed),
+            path: file.to_owned(),
+            kind,
+            size,
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            toolchains,
+            stale,
+        });
+    }
+
+    entries.sort_by_key(|e| e.modified);
+
+    Ok(entries)
+}
+
+/// Remove empty directories left in the downloads directory
+pub fn remove_empty_dirs() {
+    fn remove_empty(dir: &Path) -> bool {
+        let Ok(read_dir) = dir.read_dir() else {
+            return false;
+        };
+
+        let mut is_empty = true;
+        for entry in read_dir.filter_map(std::io::Result::ok) {
+            let path = entry.path();
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if !is_dir || !remove_empty(&path) {
+                is_empty = false;
+            }
+        }
+
+        is_empty && std::fs::remove_dir(dir).is_ok()
+    }
+
+    if let Ok(read_dir) = downloads_dir().read_dir() {
+        for entry in read_dir.filter_map(std::io::Result::ok) {
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                remove_empty(&entry.path());
+            }
+        }
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let read_dir = match dir.read_dir() {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        other => other?,
+    };
+
+    for entry in read_dir.filter_map(std::io::Result::ok) {
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+#[inline]
+fn lockfile_path(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.lock", path.display()))
+}
+
+fn is_index_expired(lockfile: &Path) -> bool {
+    std::fs::read_to_string(lockfile)
+        .ok()
+        .and_then(|s| s.trim().parse::<i64>().ok())
+        .and_then(DateTime::from_timestamp_micros)
+        .map(|lastupdated| {
+            Utc::now() >= lastupdated + Duration::hours(crate::constant::INDEX_EXPIRATION)
+        })
+        .unwrap_or(true)
+}
+
+fn referenced_blobs_in(component_index: &Path) -> Vec<PathBuf> {
+    std::fs::read_to_string(component_index)
+        .ok()
+        .and_then(|content| serde_json::from_str::<ComponentIndex>(&content).ok())
+        .map(|index| {
+            index
+                .components()
+                .iter()
+                .filter_map(|c| blob_path(&c.sha256).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Derive the toolchains of the release directory the given `file` is in,
+/// e.g. `latest/<version>/...`, `nightly/<date>/...` or `bleeding/...`
+fn release_dir_toolchains(
+    downloads_dir: &Path,
+    file: &Path,
+    installed: &[InstalledToolchain],
+) -> Vec<ToolchainSpec> {
+    let Ok(relative) = file.strip_prefix(downloads_dir) else {
+        return vec![];
+    };
+
+    let components = relative
+        .iter()
+        .map(|c| c.to_string_lossy())
+        .collect::<Vec<_>>();
+
+    // The toolchain installed under a channel name owns the release if the
+    // installed version of the channel matches
+    let channel_installed_as = |channel: ToolchainSpec, tag: &str| {
+        installed
+            .iter()
+            .find(|t| t.name == channel && t.tag.as_deref() == Some(tag))
+            .map(|t| t.name.clone())
+    };
+
+    match components.as_slice() {
+        [channel, _] if channel == "bleeding" => vec![ToolchainSpec::Bleeding],
+        [channel, version, ..] if channel == "latest" => {
+            let mut specs = vec![ToolchainSpec::Version(version.to_string())];
+            specs.extend(channel_installed_as(ToolchainSpec::Latest, version));
+            specs
+        }
+        [channel, date, ..] if channel == "nightly" => {
+            let mut specs = vec![ToolchainSpec::Version(format!("nightly-{date}"))];
+            specs.extend(channel_installed_as(ToolchainSpec::Nightly, date));
+            specs
+        }
+        _ => vec![],
+    }
+}
@@ -0,0 +1,185 @@
+use clap::{Parser, Subcommand};
+use indicatif::{HumanBytes, HumanDuration};
+use miette::IntoDiagnostic;
+use std::time::{Duration, SystemTime};
+
+use crate::cache::{self, CacheEntry, CacheEntryKind};
+use crate::utils::{parse_duration_in, parse_size};
+
+/// Manage cached downloads
+#[derive(Parser, Debug)]
+#[clap(arg_required_else_help = true)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List cached downloads and index files
+    #[clap(visible_alias = "ls")]
+    List,
+
+    /// Remove cached downloads and stale index files
+    ///
+    /// Without `--older-than` or `--max-size`, downloads not used by any
+    /// installed toolchain and expired index files are removed.
+    Prune(PruneArgs),
+}
+
+#[derive(Parser, Debug)]
+struct PruneArgs {
+    /// Remove entries older than the given age (e.g. 30d, 12h, 2w)
+    #[clap(long, value_name = "AGE", value_parser = parse_age)]
+    older_than: Option<Duration>,
+
+    /// Remove the oldest entries until the cache fits the given size (e.g. 500M, 2G)
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    /// Keep entries used by installed toolchains
+    #[clap(long)]
+    keep_installed: bool,
+
+    /// Show what would be removed without removing anything
+    #[clap(long)]
+    dry_run: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    match args.command {
+        Command::List => list(),
+        Command::Prune(args) => prune(args),
+    }
+}
+
+fn list() -> miette::Result<()> {
+    let entries = cache::entries()?;
+
+    println!("Cache directory: {}\n", cache::downloads_dir().display());
+    if entries.is_empty() {
+        println!("No cached downloads");
+        return Ok(());
+    }
+
+    let now = SystemTime::now();
+    println!("{:>10}  {:<12}  {:<24}  ENTRY", "SIZE", "AGE", "TOOLCHAIN");
+    for entry in entries.iter() {
+        let toolchains = match entry.toolchains.is_empty() {
+            true => "-".to_owned(),
+            false => entry
+                .toolchains
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+
+        let mut markers = vec![];
+        if entry.installed {
+            markers.push(console::style("installed").green().to_string());
+        }
+        if entry.stale {
+            markers.push(console::style("stale").yellow().to_string());
+        }
+        let markers = match markers.is_empty() {
+            true => String::new(),
+            false => format!(" ({})", markers.join(", ")),
+        };
+
+        println!(
+            "{:>10}  {:<12}  {:<24}  {}{}",
+            HumanBytes(entry.size).to_string(),
+            format_age(now, entry),
+            toolchains,
+            entry.display_name(),
+            markers
+        );
+    }
+
+    let total = entries.iter().map(|e| e.size).sum::<u64>();
+    println!("\nTotal: {} entries, {}", entries.len(), HumanBytes(total));
+
+    Ok(())
+}
+
+fn prune(args: PruneArgs) -> miette::Result<()> {
+    let entries = cache::entries()?;
+    let now = SystemTime::now();
+
+    let is_protected = |e: &CacheEntry| args.keep_installed && e.installed;
+    let age_of = |e: &CacheEntry| now.duration_since(e.modified).unwrap_or_default();
+
+    let (mut removals, mut kept): (Vec<_>, Vec<_>) =
+        entries.into_iter().partition(|e| match args.older_than {
+            _ if is_protected(e) => false,
+            Some(older_than) => e.stale || age_of(e) > older_than,
+            None if args.max_size.is_some() => e.stale,
+            None => !e.installed && (e.stale || e.kind == CacheEntryKind::Download),
+        });
+
+    // Remove the oldest entries until the remaining entries fit the size.
+    // `kept` is sorted by age, oldest first.
+    if let Some(max_size) = args.max_size {
+        let mut total = kept.iter().map(|e| e.size).sum::<u64>();
+        let mut i = 0;
+        while total > max_size && i < kept.len() {
+            if is_protected(&kept[i]) {
+                i += 1;
+                continue;
+            }
+            let entry = kept.remove(i);
+            total -= entry.size;
+            removals.push(entry);
+        }
+    }
+
+    if removals.is_empty() {
+        println!("Nothing to prune");
+        return Ok(());
+    }
+
+    let mut freed = 0;
+    for entry in removals.iter() {
+        if args.dry_run {
+            println!("Would remove {}", entry.display_name());
+        } else {
+            tracing::debug!("removing cache entry {}", entry.path.display());
+            entry.remove().into_diagnostic()?;
+        }
+        freed += entry.size;
+    }
+
+    if args.dry_run {
+        println!(
+            "Would remove {} entries, freeing {}",
+            removals.len(),
+            HumanBytes(freed)
+        );
+    } else {
+        cache::remove_empty_dirs();
+        println!(
+            "{}Removed {} entries, freed {}",
+            console::style(console::Emoji("✔ ", "")).green(),
+            removals.len(),
+            HumanBytes(freed)
+        );
+    }
+
+    Ok(())
+}
+
+fn format_age(now: SystemTime, entry: &CacheEntry) -> String {
+    let age = now.duration_since(entry.modified).unwrap_or_default();
+    // Drop sub-minute precision, it is noise for cached downloads
+    let age = Duration::from_secs(age.as_secs() / 60 * 60);
+    match age.is_zero() {
+        true => "just now".to_owned(),
+        false => HumanDuration(age).to_string(),
+    }
+}
+
+/// Parse an age, e.g. `30d`, `12h`, `2w`, `90m` or `45s`, hours by default
+fn parse_age(s: &str) -> Result<Duration, String> {
+    parse_duration_in(s, "h")
+}
@@ -0,0 +1,374 @@
+use clap::Parser;
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::constant::ENVNAME_MOONUP_DIST_SERVER;
+use crate::settings::Settings;
+use crate::shim;
+use crate::toolchain::{InstalledToolchain, ToolchainSpec, resolve};
+use crate::utils::{build_dist_server_api, build_http_client, dist_server_baseurl};
+
+/// Diagnose problems of the moonup environment
+///
+/// PATH, shims, library links, the active toolchain, the JavaScript runtime
+/// for older language servers and the distribution server are checked, and
+/// a fix is suggested for each problem found.
+#[derive(Parser, Debug)]
+pub struct Args {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// The result of a single check
+struct Check {
+    status: Status,
+    message: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(message: String) -> Self {
+        Self {
+            status: Status::Ok,
+            message,
+            fix: None,
+        }
+    }
+
+    fn warning(message: String, fix: String) -> Self {
+        Self {
+            status: Status::Warning,
+            message,
+            fix: Some(fix),
+        }
+    }
+
+    fn error(message: String, fix: String) -> Self {
+        Self {
+            status: Status::Error,
+            message,
+            fix: Some(fix),
+        }
+    }
+
+    fn print(&self) {
+        let icon = match self.status {
+            Status::Ok => console::style(console::Emoji("✔", "ok"))
+                .green()
+                .to_string(),
+            Status::Warning => console::style("!").yellow().to_string(),
+            Status::Error => console::style(console::Emoji("✘", "error"))
+                .red()
+                .to_string(),
+        };
+        println!("{} {}", icon, self.message);
+        if let Some(fix) = &self.fix {
+            println!("  {} {}", console::style("fix:").cyan(), fix);
+        }
+    }
+}
+
+pub async fn execute(_: Args) -> miette::Result<()> {
+    let active = ToolchainSpec::from(resolve::detect_active_toolchainspec());
+
+    let checks = [
+        check_path(),
+        check_shims(&active),
+        check_links(&active),
+        check_active_toolchain(&active),
+        check_js_runtime(&active),
+        check_dist_server().await,
+    ];
+    checks.iter().for_each(Check::print);
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    let (errors, warnings) = (count(Status::Error), count(Status::Warning));
+    if errors > 0 {
+        return Err(miette::miette!(
+            "found {} error(s) and {} warning(s)",
+            errors,
+            warnings
+        ));
+    }
+
+    match warnings {
+        0 => println!("\nNo problems found"),
+        n => println!("\nFound {} warning(s)", n),
+    }
+
+    Ok(())
+}
+
+/// Check that the shims are on PATH and not shadowed by other `moon` binaries
+fn check_path() -> Check {
+    let moon_home_bin = crate::moon_home().join("bin");
+    let moon = format!("moon{}", env::consts::EXE_SUFFIX);
+    let fix = format!("add '{}' to the front of PATH", moon_home_bin.display());
+
+    let path = env::var_os("PATH").unwrap_or_default();
+    for dir in env::split_paths(&path) {
+        if is_same_dir(&dir, &moon_home_bin) {
+            return Check::ok(format!("'{}' is on PATH", moon_home_bin.display()));
+        }
+
+        let other = dir.join(&moon);
+        if other.is_file() {
+            return Check::error(
+                format!(
+                    "'{}' comes before the moonup shims on PATH",
+                    other.display()
+                ),
+                fix,
+            );
+        }
+    }
+
+    Check::error(format!("'{}' is not on PATH", moon_home_bin.display()), fix)
+}
+
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+    a == b
+        || match (a.canonicalize(), b.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+}
+
+/// Check that every shim is a copy of the current `moonup-shim`
+fn check_shims(active: &ToolchainSpec) -> Check {
+    let shim_exe = shim::shim_exe();
+    if !shim_exe.is_file() {
+        return Check::error(
+            format!("'{}' is not found", shim_exe.display()),
+            "reinstall moonup, `moonup-shim` is shipped alongside `moonup`".to_owned(),
+        );
+    }
+
+    let moon_home_bin = crate::moon_home().join("bin");
+    let mut count = 0;
+    let mut stale = vec![];
+    for dir in [moon_home_bin.clone(), moon_home_bin.join("internal")] {
+        let Ok(read_dir) = dir.read_dir() else {
+            continue;
+        };
+
+        for entry in read_dir.filter_map(std::io::Result::ok) {
+            let path = entry.path();
+            let is_exe = !cfg!(windows) || path.extension().is_some_and(|ext| ext == "exe");
+            if !path.is_file() || !is_exe {
+                continue;
+            }
+
+            count += 1;
+            if !shim::is_current(&path, &shim_exe).unwrap_or(false) {
+                let name = path.strip_prefix(&moon_home_bin).unwrap_or(&path);
+                stale.push(match shim::shim_version(&path).ok().flatten() {
+                    Some(version) if version != env!("CARGO_PKG_VERSION") => {
+                        format!("{} (moonup {})", name.display(), version)
+                    }
+                    _ => name.display().to_string(),
+                });
+            }
+        }
+    }
+
+    if count == 0 {
+        return Check::error(
+            format!("no shims found in '{}'", moon_home_bin.display()),
+            format!("run `moonup install {active}` to install a toolchain and its shims"),
+        );
+    }
+
+    if !stale.is_empty() {
+        return Check::warning(
+            format!(
+                "{} shim(s) differ from the current moonup-shim: {}",
+                stale.len(),
+                stale.join(", ")
+            ),
+            "run `moonup shims rebuild` to pour the shims again".to_owned(),
+        );
+    }
+
+    Check::ok(format!("{} shim(s) are up to date", count))
+}
+
+/// Check that the `lib` and `include` links point at an existing toolchain
+fn check_links(active: &ToolchainSpec) -> Check {
+    let mut problems = vec![];
+    for dir in ["lib", "include"] {
+        let link = crate::moon_home().join(dir);
+        match std::fs::read_link(&link) {
+            Ok(target) if !target.is_dir() => problems.push(format!(
+                "'{}' points at missing '{}'",
+                link.display(),
+                target.display()
+            )),
+            Ok(_) => {}
+            Err(_) if link.exists() => {
+                problems.push(format!("'{}' is not a link to a toolchain", link.display()))
+            }
+            // Older toolchains don't have the `include` directory
+            Err(_) if dir == "lib" => problems.push(format!("'{}' is missing", link.display())),
+            Err(_) => {}
+        }
+    }
+
+    match problems.is_empty() {
+        true => Check::ok("'lib' and 'include' link to an installed toolchain".to_owned()),
+        false => Check::warning(
+            problems.join(", "),
+            format!("run `moonup repair {active}` to link the directories again"),
+        ),
+    }
+}
+
+/// Check that the active toolchain is installed and its core is bundled
+fn check_active_toolchain(active: &ToolchainSpec) -> Check {
+    let toolchain_dir = active.install_path();
+    if !toolchain_dir.exists() {
+        return Check::warning(
+            format!("active toolchain '{}' is not installed", active),
+            format!("run `moonup install {active}` to install it"),
+        );
+    }
+
+    let reinstall = format!("run `moonup install {active}` to reinstall it");
+    let toolchain = match InstalledToolchain::from_path(&toolchain_dir) {
+        Ok(toolchain) => toolchain,
+        Err(e) => {
+            return Check::error(
+                format!("failed to read active toolchain '{}': {}", active, e),
+                reinstall,
+            );
+        }
+    };
+
+    if toolchain.is_broken() || !toolchain.has_moon() {
+        return Check::error(
+            format!("active toolchain '{}' is broken", active),
+            reinstall,
+        );
+    }
+
+    let repair = format!("run `moonup repair {active}` to bundle the core library");
+    if toolchain.is_incomplete() {
+        return Check::error(
+            format!("active toolchain '{}' is incompletely installed", active),
+            repair,
+        );
+    }
+    if !toolchain.is_core_bundled() {
+        return Check::warning(
+            format!(
+                "core library of active toolchain '{}' is not bundled",
+                active
+            ),
+            repair,
+        );
+    }
+
+    Check::ok(format!(
+        "active toolchain '{}' is installed and its core library is bundled",
+        active
+    ))
+}
+
+/// Check that a JavaScript runtime is available if the active toolchain
+/// ships a language server written in JavaScript
+fn check_js_runtime(active: &ToolchainSpec) -> Check {
+    let bin_dir = active.install_path().join("bin");
+    if !bin_dir.is_dir() {
+        return Check::ok(
+            "skipped checking the JavaScript runtime, the active toolchain is not installed"
+                .to_owned(),
+        );
+    }
+
+    // See `runner::build_command` for the LSP delegation
+    let paths = env::join_paths([&bin_dir, &bin_dir.join("internal")]).unwrap_or_default();
+    if resolve::resolve_exe("moon-lsp", &paths).is_some() {
+        return Check::ok("the language server needs no JavaScript runtime".to_owned());
+    }
+
+    let host_paths = env::var_os("PATH").unwrap_or_default();
+    match resolve::resolve_exe("bun", &host_paths)
+        .or_else(|| resolve::resolve_exe("node", &host_paths))
+    {
+        Some(runtime) => Check::ok(format!(
+            "found JavaScript runtime '{}' for the language server",
+            runtime.display()
+        )),
+        None => Check::warning(
+            "neither 'bun' nor 'node' is found, the language server of the active toolchain won't start".to_owned(),
+            "install Bun (https://bun.sh) or Node.js (https://nodejs.org)".to_owned(),
+        ),
+    }
+}
+
+/// Check that the distribution server is reachable
+async fn check_dist_server() -> Check {
+    let baseurl = crate::auth::redact(&dist_server_baseurl());
+    let fix = format!(
+        "check your network connection, or set {ENVNAME_MOONUP_DIST_SERVER} to a reachable server"
+    );
+
+    let url = match build_dist_server_api("index.json") {
+        Ok(url) => url,
+        Err(e) => {
+            return Check::error(
+                format!("invalid distribution server '{}': {}", baseurl, e),
+                fix,
+            );
+        }
+    };
+
+    let client = match build_http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            return Check::error(
+                format!("failed to configure the HTTP client: {}", e),
+                format!(
+                    "check the [network] settings in {}",
+                    Settings::path().display()
+                ),
+            );
+        }
+    };
+
+    let request = match crate::auth::get(&client.into(), url) {
+        Ok(request) => request,
+        Err(e) => {
+            return Check::error(
+                format!("failed to read the credentials: {}", e),
+                format!(
+                    "check the [auth] settings in {}",
+                    Settings::path().display()
+                ),
+            );
+        }
+    };
+    let response = request.timeout(Duration::from_secs(10)).send().await;
+    match response {
+        Ok(response) if response.status().is_success() => {
+            Check::ok(format!("distribution server '{}' is reachable", baseurl))
+        }
+        Ok(response) => Check::error(
+            format!(
+                "distribution server '{}' responded with {}",
+                baseurl,
+                response.status()
+            ),
+            fix,
+        ),
+        Err(e) => Check::error(
+            format!("distribution server '{}' is unreachable: {}", baseurl, e),
+            fix,
+        ),
+    }
+}
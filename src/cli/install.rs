@@ -1,15 +1,13 @@
-use clap::{CommandFactory, Parser};
+use clap::Parser;
 use dialoguer::theme::ColorfulTheme;
 use miette::{Context, IntoDiagnostic};
-use std::ffi::OsString;
-#[cfg(not(target_os = "windows"))]
-use std::os::unix::fs::PermissionsExt;
-use std::path::{Path, PathBuf};
-use std::{env, process::Command};
-
-#[cfg(target_os = "windows")]
-use crate::constant::ALLOWED_EXTENSIONS;
+use std::process::Command;
+
+use crate::constant::TOOLCHAIN_INCOMPLETE_FILE;
 use crate::dist_server::schema::ChannelName;
+use crate::hooks::{self, Hook, HookContext};
+use crate::reporter::{self, Event, Stage};
+use crate::settings::Settings;
 use crate::toolchain::index::InstallRecipe;
 use crate::toolchain::resolve::detect_pinned_toolchain;
 use crate::toolchain::{ToolchainSpec, index};
@@ -31,6 +29,9 @@ pub struct Args {
     /// available versions for that channel.
     #[clap(long, short = 'l')]
     list_available: bool,
+
+    #[command(flatten)]
+    pub(super) output: super::MessageFormatArgs,
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
@@ -86,42 +87,60 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 
                     ToolchainSpec::from(picked)
                 } else {
-                    eprintln!("No available toolchain versions to select");
-                    std::process::exit(1);
+                    miette::bail!("No available toolchain versions to select");
                 }
             } else {
                 s
             }
         }
-        None => match detect_pinned_toolchain().map(ToolchainSpec::from) {
-            Some(v) => v,
-            None => {
-                let mut cmd = Args::command();
-                cmd.print_help().into_diagnostic()?;
-                std::process::exit(2);
-            }
-        },
+        None => detect_pinned_toolchain()
+            .map(ToolchainSpec::from)
+            .ok_or_else(|| {
+                miette::miette!(
+                    help = "specify a toolchain, e.g. `moonup install latest`, or pin one with `moonup pin`",
+                    "No toolchain specified and no pinned toolchain found"
+                )
+            })?,
     };
 
-    let recipe = build_installrecipe(&spec).await?.unwrap_or_else(|| {
-        eprintln!("No toolchain available for requested spec '{}'", spec);
-        std::process::exit(1);
-    });
+    let recipe = build_installrecipe(&spec)
+        .await?
+        .ok_or_else(|| miette::miette!("No toolchain available for requested spec '{}'", spec))?;
+
+    let settings = Settings::load()?;
+    let install_path = crate::moonup_home()
+        .join("toolchains")
+        .join(toolchain_install_dirname(&recipe));
+    let spec_str = spec.to_string();
+    let hook_context = HookContext {
+        spec: &spec_str,
+        version: Some(&recipe.release.version),
+        date: recipe.release.date.as_deref(),
+        path: &install_path,
+    };
 
-    println!("Installing toolchain '{}'", spec);
+    hooks::run(Hook::PreInstall, &settings, hook_context)?;
+
+    reporter::emit(Event::InstallStarted {
+        spec: spec_str.clone(),
+        version: recipe.release.version.clone(),
+        date: recipe.release.date.clone(),
+    });
     populate_install(&recipe).await?;
     post_install(&recipe)?;
     link_dirs(&recipe)?;
 
-    println!(
-        "{}Installed toolchain version '{}'",
-        console::style(console::Emoji("✔ ", "")).green(),
-        spec
-    );
-    println!(
-        "Make sure '{}' is added to your PATH",
-        crate::moon_home().join("bin").display()
-    );
+    reporter::emit(Event::Installed {
+        spec: spec_str.clone(),
+    });
+    reporter::emit(Event::Message {
+        message: format!(
+            "Make sure '{}' is added to your PATH",
+            crate::moon_home().join("bin").display()
+        ),
+    });
+
+    hooks::run(Hook::PostInstall, &settings, hook_context)?;
 
     Ok(())
 }
@@ -143,45 +162,21 @@ fn toolchain_install_dirname(recipe: &InstallRecipe) -> String {
 }
 
 // Post installation: pour shims and build the core library
+//
+// A failed core library build marks the toolchain as incomplete, it can be
+// finished with `moonup repair` without downloading the toolchain again.
 pub(super) fn post_install(recipe: &InstallRecipe) -> miette::Result<()> {
-    let args = env::args_os().collect::<Vec<_>>();
-    let mut moonup_shim_exe = env::current_exe().unwrap_or_else(|_| PathBuf::from(&args[0]));
-    let moonup_shim_name = {
-        let ext = if cfg!(windows) { ".exe" } else { "" };
-        format!("moonup-shim{}", ext)
-    };
-    moonup_shim_exe.set_file_name(moonup_shim_name);
-
     let mut toolchain_dir = crate::moonup_home();
     toolchain_dir.push("toolchains");
     toolchain_dir.push(toolchain_install_dirname(recipe));
 
-    let moon_home_bin = crate::moon_home().join("bin");
-
-    std::fs::create_dir_all(&moon_home_bin).into_diagnostic()?;
-
-    // bins
     let bin_dir = toolchain_dir.join("bin");
+    let poured = crate::shim::pour::pour(&toolchain_dir)?;
+    reporter::emit(Event::ShimsPoured { shims: poured });
 
-    let bins = find_bins(bin_dir.as_path()).wrap_err("failed to find bins")?;
-    for bin in bins {
-        tracing::debug!("pouring shim for '{}'", bin.to_string_lossy());
-        let dest = moon_home_bin.join(&bin);
-        crate::utils::replace_exe(&moonup_shim_exe, &dest)?;
-    }
-
-    // internl bins
-    let internal_bin_dir = bin_dir.join("internal");
-    if internal_bin_dir.exists() {
-        let moon_home_bin_internal = moon_home_bin.join("internal");
-        std::fs::create_dir_all(&moon_home_bin_internal).into_diagnostic()?;
-
-        let internal_bins = find_bins(internal_bin_dir.as_path())?;
-        for bin in internal_bins {
-            tracing::debug!("pouring internal shim for '{}'", bin.to_string_lossy());
-            let dest = moon_home_bin_internal.join(&bin);
-            crate::utils::replace_exe(&moonup_shim_exe, &dest)?;
-        }
+    // Let the shims call back into this moonup
+    if let Err(e) = crate::shim::record_moonup_exe() {
+        tracing::warn!("failed to record the moonup executable: {}", e);
     }
 
     // Build core library
@@ -197,7 +192,7 @@ pub(super) fn post_install(recipe: &InstallRecipe) -> miette::Result<()> {
         }
     });
 
-    let mut cmd = Command::new(actual_moon_exe);
+    let mut cmd = Command::new(&actual_moon_exe);
 
     let bundle_dir_arg = if recipe.release.bundle_source_dir.unwrap_or(false) {
         "--source-dir"
@@ -211,55 +206,54 @@ pub(super) fn post_install(recipe: &InstallRecipe) -> miette::Result<()> {
     cmd.arg("--all");
     cmd.env("PATH", bin_dir.display().to_string());
     tracing::debug!("running command: {:?}", cmd);
-    cmd.status().into_diagnostic()?;
 
-    Ok(())
-}
+    let reporter = reporter::reporter("core library");
+    reporter.on_stage(Stage::Bundle);
+    let output = cmd.output();
+    reporter.on_complete();
+
+    let (status, log) = match output {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::debug!("bundle stdout: {}", stdout);
+            tracing::debug!("bundle stderr: {}", stderr);
+            (Some(output.status), format!("{}{}", stdout, stderr))
+        }
+        Err(e) => (
+            None,
+            format!("failed to run {}: {}", actual_moon_exe.display(), e),
+        ),
+    };
+    let success = status.is_some_and(|s| s.success());
+    reporter::emit(Event::Bundled {
+        success,
+        status: status
+            .map(|s| s.to_string())
+            .unwrap_or("not started".to_owned()),
+    });
 
-fn find_bins(dir: &Path) -> miette::Result<Vec<OsString>> {
-    let bins = dir
-        .read_dir()
-        .into_diagnostic()
-        .wrap_err(format!("cannot read dir: {}", dir.display()))?
-        .filter_map(std::io::Result::ok)
-        .filter_map(|e| {
-            let path = e.path();
-            let name = e.file_name();
-            let ext = path.extension();
-
-            let is_file = e
-                .file_type()
+    let marker = toolchain_dir.join(TOOLCHAIN_INCOMPLETE_FILE);
+    if success {
+        if marker.exists() {
+            std::fs::remove_file(&marker)
                 .into_diagnostic()
-                .map(|t| t.is_file())
-                .unwrap_or(false);
-
-            if is_file {
-                #[cfg(target_os = "windows")]
-                {
-                    ext.and_then(|ext| {
-                        let ext = ext.to_str().unwrap_or_default();
-                        ALLOWED_EXTENSIONS.contains(&ext).then_some(name)
-                    })
-                }
-
-                #[cfg(not(target_os = "windows"))]
-                {
-                    // Skip if the file has an extension (.h, .a. .o, etc.)
-                    if ext.is_some() {
-                        return None;
-                    }
-
-                    std::fs::set_permissions(e.path(), std::fs::Permissions::from_mode(0o755))
-                        .unwrap();
-                    Some(name)
-                }
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>();
+                .wrap_err(format!("failed to remove {}", marker.display()))?;
+        }
+        return Ok(());
+    }
 
-    Ok(bins)
+    std::fs::write(&marker, &log)
+        .into_diagnostic()
+        .wrap_err(format!("failed to write {}", marker.display()))?;
+
+    let name = toolchain_install_dirname(recipe);
+    Err(miette::miette!(
+        help = format!("run `moonup repair {name}` to bundle the core library again"),
+        "failed to bundle the core library of toolchain '{}'\n\n{}",
+        name,
+        log.trim()
+    ))
 }
 
 // Link the library directory to `MOON_HOME`/lib
@@ -271,7 +265,7 @@ fn find_bins(dir: &Path) -> miette::Result<Vec<OsString>> {
 // always looks for the core library in `MOON_HOME`/lib/core.
 //
 // Discussion: https://github.com/chawyehsu/moonup/issues/7
-fn link_dirs(recipe: &InstallRecipe) -> miette::Result<()> {
+pub(super) fn link_dirs(recipe: &InstallRecipe) -> miette::Result<()> {
     let dirs = ["lib", "include"];
     let toolchains_dir = crate::moonup_home().join("toolchains");
 
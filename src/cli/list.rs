@@ -1,14 +1,106 @@
+use chrono::{DateTime, Local, Utc};
 use clap::Parser;
+use indicatif::HumanBytes;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+use std::path::PathBuf;
 
 use crate::toolchain::{InstalledToolchain, ToolchainSpec, installed_toolchains, resolve};
 
 /// List installed and active toolchains
 #[derive(Parser, Debug)]
-pub struct Args {}
+pub struct Args {
+    /// Show the size, install date, resolved version and health of each toolchain
+    #[clap(long, short = 'l')]
+    long: bool,
 
-pub async fn execute(_: Args) -> miette::Result<()> {
+    /// Print the toolchains as JSON
+    #[clap(long)]
+    json: bool,
+}
+
+/// Details of an installed toolchain
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolchainDetails {
+    name: String,
+    path: PathBuf,
+    version: Option<String>,
+    date: Option<String>,
+    size: u64,
+    installed_at: Option<DateTime<Utc>>,
+    is_default: bool,
+    is_active: bool,
+    has_moon: bool,
+    core_bundled: bool,
+    incomplete: bool,
+    broken: bool,
+}
+
+impl ToolchainDetails {
+    fn new(toolchain: &InstalledToolchain, default: Option<&str>, active: &str) -> Self {
+        let (version, date) = match (&toolchain.receipt, &toolchain.name) {
+            (Some(receipt), _) => (
+                Some(receipt.release.version.clone()),
+                receipt.release.date.clone(),
+            ),
+            // Toolchains installed without a receipt only know their tag
+            (None, ToolchainSpec::Nightly) => (None, toolchain.tag.clone()),
+            (None, ToolchainSpec::Version(v)) => match v.strip_prefix("nightly-") {
+                Some(date) => (None, Some(date.to_owned())),
+                None => (Some(v.clone()), None),
+            },
+            (None, _) => (toolchain.tag.clone(), None),
+        };
+
+        let installed_at = match &toolchain.receipt {
+            Some(receipt) => Some(receipt.installed_at),
+            None => toolchain
+                .path
+                .metadata()
+                .and_then(|m| m.created().or(m.modified()))
+                .ok()
+                .map(DateTime::<Utc>::from),
+        };
+
+        Self {
+            name: toolchain.name.to_string(),
+            path: toolchain.path.clone(),
+            version,
+            date,
+            size: crate::fs::dir_size(&toolchain.path).unwrap_or_default(),
+            installed_at,
+            is_default: default.is_some_and(|d| toolchain.name == ToolchainSpec::from(d)),
+            is_active: toolchain.name == ToolchainSpec::from(active),
+            has_moon: toolchain.has_moon(),
+            core_bundled: toolchain.is_core_bundled(),
+            incomplete: toolchain.is_incomplete(),
+            broken: toolchain.is_broken(),
+        }
+    }
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
     let installs = installed_toolchains()?;
 
+    if args.json {
+        let default = resolve::detect_default_toolchain();
+        let active = resolve::detect_active_toolchainspec();
+        let details = installs
+            .iter()
+            .map(|t| ToolchainDetails::new(t, default.as_deref(), &active))
+            .collect::<Vec<_>>();
+
+        let json = serde_json::json!({
+            "moonupHome": crate::moonup_home(),
+            "default": default,
+            "active": active,
+            "toolchains": details,
+        });
+        println!("{}", serde_json::to_string_pretty(&json).into_diagnostic()?);
+        return Ok(());
+    }
+
     println!("Moonup home: {}\n", crate::moonup_home().display());
     if installs.is_empty() {
         println!("No toolchains installed");
@@ -16,22 +108,34 @@ pub async fn execute(_: Args) -> miette::Result<()> {
         let default = resolve::detect_default_toolchain();
 
         println!("Installed toolchains:");
-        for i in installs {
-            let is_default = default
-                .as_deref()
-                .map(|d| i.name == ToolchainSpec::from(d))
-                .unwrap_or(false);
-
-            let tags = match (is_default, i.tag.as_deref()) {
-                (true, Some(tag)) => {
-                    Some(format!(" ({}, {})", console::style("default").cyan(), tag))
-                }
-                (true, None) => Some(format!(" ({})", console::style("default").cyan())),
-                (false, Some(tag)) => Some(format!(" ({})", tag)),
-                (false, None) => None,
-            };
-
-            println!("  {}{}", i.name, tags.unwrap_or_default());
+        if args.long {
+            print_long(&installs, default.as_deref());
+        } else {
+            for i in installs {
+                let is_default = default
+                    .as_deref()
+                    .map(|d| i.name == ToolchainSpec::from(d))
+                    .unwrap_or(false);
+
+                let tags = match (is_default, i.tag.as_deref()) {
+                    (true, Some(tag)) => {
+                        Some(format!(" ({}, {})", console::style("default").cyan(), tag))
+                    }
+                    (true, None) => Some(format!(" ({})", console::style("default").cyan())),
+                    (false, Some(tag)) => Some(format!(" ({})", tag)),
+                    (false, None) => None,
+                };
+
+                let broken = if i.is_broken() {
+                    format!(" {}", console::style("[broken]").red())
+                } else if i.is_incomplete() {
+                    format!(" {}", console::style("[incomplete]").yellow())
+                } else {
+                    String::new()
+                };
+
+                println!("  {}{}{}", i.name, tags.unwrap_or_default(), broken);
+            }
         }
 
         let active = resolve::detect_active_toolchain();
@@ -41,3 +145,55 @@ pub async fn execute(_: Args) -> miette::Result<()> {
 
     Ok(())
 }
+
+fn print_long(installs: &[InstalledToolchain], default: Option<&str>) {
+    let active = resolve::detect_active_toolchainspec();
+
+    println!(
+        "  {:<24}  {:<24}  {:<10}  {:>10}  {:<16}  {:<4}  {:<4}  STATUS",
+        "NAME", "VERSION", "DATE", "SIZE", "INSTALLED", "MOON", "CORE"
+    );
+    for t in installs.iter() {
+        let details = ToolchainDetails::new(t, default, &active);
+
+        let name = match details.is_default {
+            true => format!("{} (default)", details.name),
+            false => details.name.clone(),
+        };
+        let installed_at = details
+            .installed_at
+            .map(|t| {
+                DateTime::<Local>::from(t)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or("-".to_owned());
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+
+        let status = if details.broken {
+            console::style("broken (missing bin/)").red().to_string()
+        } else if details.incomplete {
+            console::style("incomplete (run `moonup repair`)")
+                .yellow()
+                .to_string()
+        } else if !details.has_moon {
+            console::style("missing bin/moon").yellow().to_string()
+        } else if !details.core_bundled {
+            console::style("core not bundled").yellow().to_string()
+        } else {
+            console::style("ok").green().to_string()
+        };
+
+        println!(
+            "  {:<24}  {:<24}  {:<10}  {:>10}  {:<16}  {:<4}  {:<4}  {}",
+            name,
+            details.version.as_deref().unwrap_or("-"),
+            details.date.as_deref().unwrap_or("-"),
+            HumanBytes(details.size).to_string(),
+            installed_at,
+            yes_no(details.has_moon),
+            yes_no(details.core_bundled),
+            status
+        );
+    }
+}
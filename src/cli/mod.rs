@@ -5,17 +5,23 @@ use tracing_subscriber::{
     EnvFilter, filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt,
 };
 
+use crate::reporter::{self, Event, MessageFormat};
 use crate::toolchain::ToolchainSpec;
 
+mod cache;
 mod completions;
 mod default;
+mod doctor;
 mod install;
 mod list;
 mod pin;
+mod repair;
 mod run;
 mod selfupdate;
+mod shims;
 mod uninstall;
 mod update;
+mod verify;
 mod which;
 
 #[derive(Debug, Parser)]
@@ -38,12 +44,24 @@ pub struct Cli {
     verbose: Verbosity,
 }
 
+/// The output options of the commands reporting their progress as events
+#[derive(Debug, Clone, Copy, clap::Args)]
+struct MessageFormatArgs {
+    /// Message format of the progress and result events
+    #[clap(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+}
+
 #[derive(Debug, Parser)]
 pub enum Command {
+    Cache(cache::Args),
+
     Completions(completions::Args),
 
     Default(default::Args),
 
+    Doctor(doctor::Args),
+
     #[clap(visible_alias = "i")]
     Install(install::Args),
 
@@ -52,21 +70,43 @@ pub enum Command {
 
     Pin(pin::Args),
 
+    Repair(repair::Args),
+
     #[clap(visible_alias = "x")]
     Run(run::Args),
 
     #[cfg_attr(not(feature = "self_update"), clap(hide = true))]
     SelfUpdate(selfupdate::Args),
 
+    Shims(shims::Args),
+
     #[clap(alias = "rm")]
     Uninstall(uninstall::Args),
 
     #[clap(visible_alias = "u")]
     Update(update::Args),
 
+    Verify(verify::Args),
+
     Which(which::Args),
 }
 
+impl Command {
+    /// The message format requested for the command, commands without
+    /// events only print human-readable output
+    fn message_format(&self) -> MessageFormat {
+        let args = match self {
+            Command::Install(args) => args.output,
+            Command::SelfUpdate(args) => args.output,
+            Command::Shims(args) => return args.message_format(),
+            Command::Uninstall(args) => args.output,
+            Command::Update(args) => args.output,
+            _ => return MessageFormat::Human,
+        };
+        args.message_format
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 struct ToolchainSpecValueParser;
 
@@ -108,17 +148,36 @@ impl Default for ToolchainSpecValueParser {
 pub async fn start() -> miette::Result<()> {
     let args = Cli::parse();
     setup_logger(args.verbose.tracing_level_filter())?;
+    let message_format = args.command.message_format();
+    reporter::set_message_format(message_format);
+
+    let result = execute(args.command).await;
+    if let Err(err) = &result
+        && message_format == MessageFormat::Json
+    {
+        reporter::emit(Event::Error {
+            message: err.to_string(),
+        });
+    }
+    result
+}
 
-    match args.command {
+async fn execute(command: Command) -> miette::Result<()> {
+    match command {
+        Command::Cache(args) => cache::execute(args).await?,
         Command::Completions(args) => completions::execute(args).await?,
         Command::Default(args) => default::execute(args).await?,
+        Command::Doctor(args) => doctor::execute(args).await?,
         Command::Install(args) => install::execute(args).await?,
         Command::List(args) => list::execute(args).await?,
         Command::Pin(args) => pin::execute(args).await?,
+        Command::Repair(args) => repair::execute(args).await?,
         Command::Run(args) => run::execute(args).await?,
         Command::SelfUpdate(args) => selfupdate::execute(args).await?,
+        Command::Shims(args) => shims::execute(args).await?,
         Command::Uninstall(args) => uninstall::execute(args).await?,
         Command::Update(args) => update::execute(args).await?,
+        Command::Verify(args) => verify::execute(args).await?,
         Command::Which(args) => which::execute(args).await?,
     }
     Ok(())
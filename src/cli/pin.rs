@@ -5,7 +5,10 @@ use std::env;
 
 use crate::{
     constant,
-    toolchain::{ToolchainSpec, resolve::resolve_toolchain_file},
+    toolchain::{
+        ToolchainSpec,
+        resolve::{ToolchainFile, resolve_toolchain_file},
+    },
 };
 
 use super::ToolchainSpecValueParser;
@@ -58,7 +61,16 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         current_dir.join(crate::constant::TOOLCHAIN_FILE)
     });
 
-    tokio::fs::write(&toolchain_file, format!("{}\n", version))
+    // Keep the options and comments of an existing toolchain file
+    let mut content = format!("{}\n", version);
+    if let Ok(existing) = tokio::fs::read_to_string(&toolchain_file).await {
+        existing
+            .lines()
+            .filter(|line| !ToolchainFile::is_toolchain_line(line))
+            .for_each(|line| content.push_str(&format!("{}\n", line)));
+    }
+
+    tokio::fs::write(&toolchain_file, content.trim_end().to_owned() + "\n")
         .await
         .into_diagnostic()?;
 
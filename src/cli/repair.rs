@@ -0,0 +1,51 @@
+use clap::Parser;
+
+use crate::toolchain::{InstalledToolchain, ToolchainSpec};
+
+use super::ToolchainSpecValueParser;
+use super::install::{link_dirs, post_install};
+
+/// Finish an incomplete toolchain install
+///
+/// Shims are poured and the core library is bundled again from the installed
+/// files, nothing is downloaded.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The toolchain to repair
+    #[clap(value_parser = ToolchainSpecValueParser::new())]
+    toolchain: ToolchainSpec,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let spec = args.toolchain;
+    let toolchain_dir = spec.install_path();
+    if !toolchain_dir.exists() {
+        return Err(miette::miette!(
+            help = format!("run `moonup install {spec}` to install it"),
+            "toolchain '{}' is not installed",
+            spec
+        ));
+    }
+
+    let installed = InstalledToolchain::from_path(&toolchain_dir)?;
+    let Some(receipt) = &installed.receipt else {
+        return Err(miette::miette!(
+            help = format!("run `moonup install {spec}` to reinstall the toolchain"),
+            "cannot repair toolchain '{}' without an install receipt",
+            spec
+        ));
+    };
+    let recipe = receipt.to_recipe();
+
+    println!("Repairing toolchain '{}'", spec);
+    post_install(&recipe)?;
+    link_dirs(&recipe)?;
+
+    println!(
+        "{}Repaired toolchain '{}'",
+        console::style(console::Emoji("✔ ", "")).green(),
+        spec
+    );
+
+    Ok(())
+}
@@ -15,12 +15,12 @@ pub struct Args {
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
-    let mut cmd = runner::build_command(args.toolchain, args.command)
+    let cmd = runner::build_command(args.toolchain, args.command)
         .map_err(|e| miette::miette!("Failed to build command: {}", e))?;
 
-    match cmd.status() {
-        Err(e) => Err(miette::miette!("Failed to run command: {}", e)),
+    match runner::exec(cmd) {
+        Err(e) => Err(miette::miette!("{}", e)),
         Ok(status) if status.success() => Ok(()),
-        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Ok(status) => runner::exit_with(status),
     }
 }
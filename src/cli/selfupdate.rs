@@ -1,51 +1,78 @@
 use clap::Parser;
-use miette::IntoDiagnostic;
+use miette::{Context, IntoDiagnostic};
+use serde::Deserialize;
 use std::env;
 use std::path::PathBuf;
 use tokio::io::AsyncReadExt;
 
 use crate::archive;
+use crate::reporter::{self, Event, Stage};
 use crate::utils::{self, build_http_client_with_retry};
 
+/// The GitHub API endpoint of the latest moonup release
+const LATEST_RELEASE_API: &str = "https://api.github.com/repos/chawyehsu/moonup/releases/latest";
+
 /// Update Moonup to the latest version
+///
+/// The shims in `MOON_HOME` are poured again from the new `moonup-shim`.
 #[derive(Parser, Debug)]
-pub struct Args {}
+pub struct Args {
+    #[command(flatten)]
+    pub(super) output: super::MessageFormatArgs,
+}
+
+/// A GitHub release
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+/// A downloadable asset of a GitHub release
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
 
 pub async fn execute(_: Args) -> miette::Result<()> {
     let current_version = env!("CARGO_PKG_VERSION");
 
-    // self_update does not work well with async, see:
-    //   https://github.com/jaemk/self_update/issues/44
-    let latest_release = tokio::task::spawn_blocking(move || {
-        let updater = self_update::backends::github::Update::configure()
-            .repo_owner("chawyehsu")
-            .repo_name("moonup")
-            .bin_name(env!("CARGO_PKG_NAME"))
-            .current_version(current_version)
-            .build()?;
-        updater.get_latest_release()
-    })
-    .await
-    .expect("spawn blocking task")
-    .into_diagnostic()?;
-
-    let is_greater =
-        self_update::version::bump_is_greater(current_version, &latest_release.version)
-            .ok()
-            .unwrap_or(false);
+    // Fetch the release with our own client, so that the proxy and TLS
+    // settings apply
+    let client = build_http_client_with_retry()?;
+    let latest_release = client
+        .get(LATEST_RELEASE_API)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await
+        .into_diagnostic()?
+        .error_for_status()
+        .into_diagnostic()?
+        .json::<Release>()
+        .await
+        .into_diagnostic()
+        .wrap_err("failed to read the latest moonup release")?;
+    let latest_version = latest_release.tag_name.trim_start_matches('v').to_owned();
+
+    let is_greater = self_update::version::bump_is_greater(current_version, &latest_version)
+        .ok()
+        .unwrap_or(false);
 
     // Environment variable to force self-update for testing purposes
     let env_test_trigger = std::env::var("MOONUP_TEST_FORCE_SELFUPDATE").is_ok();
 
     if !is_greater && !env_test_trigger {
-        println!("moonup is already up-to-date");
+        reporter::emit(Event::SelfUpToDate {
+            version: current_version.to_owned(),
+        });
         return Ok(());
     }
 
-    println!(
-        "Updating moonup: {} -> {}",
-        current_version, latest_release.version
-    );
+    reporter::emit(Event::SelfUpdateStarted {
+        from: current_version.to_owned(),
+        to: latest_version.clone(),
+    });
 
     let assets = latest_release
         .assets
@@ -54,9 +81,14 @@ pub async fn execute(_: Args) -> miette::Result<()> {
         .collect::<Vec<_>>();
 
     tracing::trace!("moonup assets: {:?}", assets);
-    assert_eq!(assets.len(), 2, "expected two assets");
+    miette::ensure!(
+        assets.len() == 2,
+        "expected an archive and its checksum for {} in release {}, found {} assets",
+        self_update::get_target(),
+        latest_release.tag_name,
+        assets.len()
+    );
 
-    let client = build_http_client_with_retry();
     let temp_dir = self_update::TempDir::with_prefix("moonup").into_diagnostic()?;
 
     let mut sha256_actual = String::new();
@@ -65,14 +97,12 @@ pub async fn execute(_: Args) -> miette::Result<()> {
     let extract_to = temp_dir.path();
 
     for asset in assets {
-        let url = format!(
-            "https://github.com/chawyehsu/moonup/releases/download/v{}/{}",
-            latest_release.version, asset.name
-        );
-        let url = url::Url::parse(url.as_str()).into_diagnostic()?;
+        let url = url::Url::parse(&asset.browser_download_url).into_diagnostic()?;
         tracing::debug!("downloading {} from {}", asset.name, url);
 
-        let mut reader = utils::url_to_reader(url, &client, None).await?;
+        let reporter = reporter::reporter(&asset.name);
+        reporter.on_stage(Stage::Download);
+        let mut reader = utils::url_to_reader(url, &client, Some(reporter.clone())).await?;
 
         if asset.name.ends_with(".sha256") {
             let mut content = String::new();
@@ -91,9 +121,20 @@ pub async fn execute(_: Args) -> miette::Result<()> {
 
             sha256_actual = format!("{:x}", sha256);
         }
+        reporter.on_complete();
     }
 
-    assert_eq!(sha256_actual, sha256_expected, "SHA256 checksum mismatch");
+    miette::ensure!(
+        sha256_actual == sha256_expected,
+        "Checksum mismatch for moonup {}\nExpected: {}\n  Actual: {}",
+        latest_version,
+        sha256_expected,
+        sha256_actual
+    );
+    reporter::emit(Event::ChecksumVerified {
+        component: env!("CARGO_PKG_NAME").to_owned(),
+        sha256: sha256_actual,
+    });
 
     let args = env::args_os().collect::<Vec<_>>();
     for bin in ["moonup", "moonup-shim"] {
@@ -107,5 +148,12 @@ pub async fn execute(_: Args) -> miette::Result<()> {
 
         utils::replace_exe(&src, &dst)?;
     }
+
+    // Poured shims are copies of the old `moonup-shim`
+    super::shims::reconcile()?;
+
+    reporter::emit(Event::SelfUpdated {
+        version: latest_version,
+    });
     Ok(())
 }
@@ -0,0 +1,72 @@
+use clap::{Parser, Subcommand};
+
+use crate::reporter::{self, Event, MessageFormat};
+use crate::shim::pour;
+
+/// Manage the shims in `MOON_HOME`
+#[derive(Parser, Debug)]
+#[clap(arg_required_else_help = true)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Reconcile the shims with the installed toolchains
+    ///
+    /// Shims are created for the executables of all installed toolchains,
+    /// outdated shims are refreshed and shims of executables no longer
+    /// provided by any toolchain are removed.
+    Rebuild {
+        #[command(flatten)]
+        output: super::MessageFormatArgs,
+    },
+}
+
+impl Args {
+    pub(super) fn message_format(&self) -> MessageFormat {
+        match &self.command {
+            Command::Rebuild { output } => output.message_format,
+        }
+    }
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    match args.command {
+        Command::Rebuild { .. } => rebuild(),
+    }
+}
+
+fn rebuild() -> miette::Result<()> {
+    report(pour::rebuild()?);
+    Ok(())
+}
+
+/// Reconcile the shims after toolchains are uninstalled or updated, only
+/// changes are reported
+pub(super) fn reconcile() -> miette::Result<()> {
+    let rebuilt = pour::rebuild()?;
+    if !rebuilt.is_empty() {
+        report(rebuilt);
+    }
+    Ok(())
+}
+
+fn report(rebuilt: pour::Rebuilt) {
+    for name in rebuilt.created.iter() {
+        tracing::info!("created shim '{}'", name);
+    }
+    for name in rebuilt.refreshed.iter() {
+        tracing::info!("refreshed shim '{}'", name);
+    }
+    for name in rebuilt.removed.iter() {
+        tracing::info!("removed shim '{}'", name);
+    }
+
+    reporter::emit(Event::ShimsRebuilt {
+        created: rebuilt.created,
+        refreshed: rebuilt.refreshed,
+        removed: rebuilt.removed,
+    });
+}
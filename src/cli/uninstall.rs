@@ -1,11 +1,17 @@
 use clap::{CommandFactory, Parser};
 use dialoguer::{MultiSelect, theme};
 use miette::{Context, IntoDiagnostic};
+use std::collections::HashSet;
+use std::path::PathBuf;
 use tracing::instrument;
 
 use crate::{
-    constant,
-    toolchain::{ToolchainSpec, installed_toolchains},
+    cache, constant,
+    dist_server::schema::ChannelName,
+    hooks::{self, Hook, HookContext},
+    reporter::{self, Event},
+    settings::Settings,
+    toolchain::{InstalledToolchain, ToolchainSpec, installed_toolchains},
 };
 
 /// Uninstall a MoonBit toolchain.
@@ -25,20 +31,20 @@ pub struct Args {
     /// Use this flag to keep the cached downloads.
     #[clap(long)]
     keep_cache: bool,
+
+    #[command(flatten)]
+    pub(super) output: super::MessageFormatArgs,
 }
 
 #[instrument(name = "uninstall", skip(args), fields(toolchain = ?args.toolchain))]
 pub async fn execute(args: Args) -> miette::Result<()> {
     if args.clear {
-        let download_dir = crate::moonup_home().join("downloads");
+        let download_dir = cache::downloads_dir();
         tracing::info!("removing all cached downloads {}", download_dir.display());
         crate::fs::empty_dir(&download_dir)
             .into_diagnostic()
             .wrap_err("failed to clear cached downloads")?;
-        println!(
-            "{} Cleared all cached downloads",
-            console::style(console::Emoji("✔ ", "")).green()
-        );
+        reporter::emit(Event::CacheCleared);
         return Ok(());
     }
 
@@ -72,6 +78,9 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         std::process::exit(1);
     }
 
+    let settings = Settings::load()?;
+    let mut uninstalled = false;
+    let mut hook_failures = vec![];
     for toolchain in toolchains {
         let toolchain_dir = toolchain.install_path();
         if !toolchain_dir.exists() {
@@ -79,39 +88,55 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             continue;
         }
 
+        let installed = InstalledToolchain::from_path(&toolchain_dir)?;
+        let spec = toolchain.to_string();
+        let hook_context = HookContext {
+            spec: &spec,
+            version: installed
+                .receipt
+                .as_ref()
+                .map(|r| r.release.version.as_str())
+                .or(installed.tag.as_deref()),
+            date: installed
+                .receipt
+                .as_ref()
+                .and_then(|r| r.release.date.as_deref()),
+            path: &toolchain_dir,
+        };
+        hooks::run(Hook::PreUninstall, &settings, hook_context)?;
+
         tracing::info!("uninstalling toolchain {}", toolchain);
 
         if !args.keep_cache {
-            let mut download_dir = crate::moonup_home().join("downloads");
-
-            match &toolchain {
-                ToolchainSpec::Bleeding => download_dir.push("bleeding"),
-                ToolchainSpec::Version(v) => {
-                    if v.starts_with("nightly") {
-                        download_dir.push("nightly");
-                        let date = v
-                            .split_once('-')
-                            .expect("should split nightly version str")
-                            .1;
-                        download_dir.push(date);
-                    } else {
-                        download_dir.push("latest");
-                        download_dir.push(v);
-                    }
+            let (download_dir, blobs) = match &installed.receipt {
+                Some(receipt) => {
+                    let channel = ChannelName::from(&toolchain);
+                    let blobs = receipt
+                        .components
+                        .iter()
+                        .filter_map(|c| cache::blob_path(&c.sha256).ok())
+                        .filter(|p| p.is_file())
+                        .collect::<Vec<_>>();
+                    (cache::release_dir(&channel, &receipt.release)?, blobs)
+                }
+                None => {
+                    let download_dir = legacy_release_dir(&installed);
+                    let blobs = cache::referenced_blobs(&download_dir);
+                    (download_dir, blobs)
                 }
-                _ => {
-                    if toolchain.is_nightly() {
-                        download_dir.push("nightly");
-                    } else {
-                        download_dir.push("latest");
-                    }
-
-                    let version_file = toolchain_dir.join("version");
-                    let version = tokio::fs::read_to_string(version_file)
-                        .await
-                        .into_diagnostic()?;
-                    download_dir.push(version.trim());
+            };
+
+            // Installs of the same release share the cached downloads
+            let in_use = blobs_in_use(&toolchain)?;
+            for blob in blobs {
+                if in_use.contains(&blob) {
+                    tracing::info!("keeping cached download {} in use", blob.display());
+                    continue;
                 }
+                tracing::info!("removing cached download {}", blob.display());
+                let _ = std::fs::remove_file(blob).inspect_err(|e| {
+                    tracing::warn!("failed to remove cached download: {}", e);
+                });
             }
 
             tracing::info!("removing cached downloads {}", download_dir.display());
@@ -121,14 +146,70 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         }
 
         tracing::debug!("removing toolchain from {}", toolchain_dir.display());
-        crate::fs::remove_dir_all(toolchain_dir).into_diagnostic()?;
+        crate::fs::remove_dir_all(&toolchain_dir).into_diagnostic()?;
+
+        uninstalled = true;
+        reporter::emit(Event::Uninstalled { spec: spec.clone() });
+        if let Err(err) = hooks::run(Hook::PostUninstall, &settings, hook_context) {
+            hook_failures.push(err);
+        }
+    }
 
-        println!(
-            "{} Uninstalled toolchain {}",
-            console::style(console::Emoji("✔ ", "")).green(),
-            console::style(&toolchain).yellow().bright()
-        );
+    // Remove the shims of executables no other toolchain provides
+    if uninstalled {
+        super::shims::reconcile()?;
+    }
+
+    hooks::check_failures(hook_failures)
+}
+
+/// Collect the cached downloads referenced by the installed toolchains other
+/// than the given one
+fn blobs_in_use(toolchain: &ToolchainSpec) -> miette::Result<HashSet<PathBuf>> {
+    let mut blobs = HashSet::new();
+    for installed in installed_toolchains()? {
+        if &installed.name == toolchain {
+            continue;
+        }
+        match &installed.receipt {
+            Some(receipt) => blobs.extend(
+                receipt
+                    .components
+                    .iter()
+                    .filter_map(|c| cache::blob_path(&c.sha256).ok()),
+            ),
+            None => blobs.extend(cache::referenced_blobs(&legacy_release_dir(&installed))),
+        }
+    }
+
+    Ok(blobs)
+}
+
+/// Derive the cached release directory of a toolchain installed without a
+/// receipt from its install name and version stub
+fn legacy_release_dir(installed: &InstalledToolchain) -> PathBuf {
+    let mut download_dir = cache::downloads_dir();
+
+    match &installed.name {
+        ToolchainSpec::Bleeding => download_dir.push("bleeding"),
+        ToolchainSpec::Version(v) => {
+            if let Some(date) = v.strip_prefix("nightly-") {
+                download_dir.push("nightly");
+                download_dir.push(date);
+            } else {
+                download_dir.push("latest");
+                download_dir.push(v);
+            }
+        }
+        name => {
+            if name.is_nightly() {
+                download_dir.push("nightly");
+            } else {
+                download_dir.push("latest");
+            }
+            download_dir.push(installed.tag.as_deref().unwrap_or("unknown"));
+        }
     }
 
-    Ok(())
+    download_dir
 }
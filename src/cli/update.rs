@@ -1,62 +1,87 @@
 use clap::Parser;
-use std::path::PathBuf;
 
 use crate::cli::install::post_install;
-use crate::toolchain::ToolchainSpec;
+use crate::hooks::{self, Hook, HookContext};
+use crate::reporter::{self, Event};
+use crate::settings::Settings;
 use crate::toolchain::index::build_installrecipe;
 use crate::toolchain::package::populate_install;
+use crate::toolchain::{InstalledToolchain, ToolchainSpec};
 
 /// Update MoonBit toolchains
 #[derive(Parser, Debug)]
-pub struct Args {}
+pub struct Args {
+    #[command(flatten)]
+    pub(super) output: super::MessageFormatArgs,
+}
 
 pub async fn execute(_: Args) -> miette::Result<()> {
-    let mut path = crate::moonup_home();
-    path.push("toolchains");
-
-    update_toolchain(&mut path, &ToolchainSpec::Latest).await?;
-    update_toolchain(&mut path, &ToolchainSpec::Nightly).await?;
+    let mut hook_failures = vec![];
+    update_toolchain(&ToolchainSpec::Latest, &mut hook_failures).await?;
+    update_toolchain(&ToolchainSpec::Nightly, &mut hook_failures).await?;
 
-    Ok(())
+    hooks::check_failures(hook_failures)
 }
 
-async fn update_toolchain(path: &mut PathBuf, spec: &ToolchainSpec) -> miette::Result<()> {
+/// Update the given toolchain if a newer release is available
+///
+/// A failed post-update hook is pushed to `hook_failures` instead of
+/// failing the update.
+async fn update_toolchain(
+    spec: &ToolchainSpec,
+    hook_failures: &mut Vec<miette::Report>,
+) -> miette::Result<()> {
     let name = spec.as_str();
-    path.push(name);
-    path.push("version");
+    let toolchain_dir = spec.install_path();
 
-    let result = match tokio::fs::read_to_string(&path).await {
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-            println!("{name} toolchain is not installed, run 'moonup install {name}' to install");
-            Ok(())
-        }
-        Err(e) => Err(miette::miette!(e).wrap_err("failed to read version file")),
-        Ok(local_ver) => {
-            let recipe = build_installrecipe(spec)
-                .await?
-                .expect("should have recipe");
-
-            let should_update = match (spec, local_ver.trim()) {
-                (ToolchainSpec::Bleeding, _) => true,
-                (ToolchainSpec::Latest, local) => local != recipe.release.version.as_str(),
-                (ToolchainSpec::Nightly, local) => {
-                    local != recipe.release.date.as_deref().expect("should have date")
-                }
-                _ => false,
-            };
-
-            if should_update {
-                println!("Updating the {} toolchain", name);
-                populate_install(&recipe).await?;
-                post_install(&recipe)?;
-            } else {
-                println!("The {} toolchain is up to date", name);
-            }
-            Ok(())
+    if !toolchain_dir.exists() {
+        reporter::emit(Event::NotInstalled {
+            spec: name.to_owned(),
+        });
+        return Ok(());
+    }
+
+    let installed = InstalledToolchain::from_path(&toolchain_dir)?;
+    let recipe = build_installrecipe(spec)
+        .await?
+        .expect("should have recipe");
+
+    let should_update = match (spec, installed.tag.as_deref()) {
+        (ToolchainSpec::Bleeding, _) => true,
+        (ToolchainSpec::Latest, Some(local)) => local != recipe.release.version.as_str(),
+        (ToolchainSpec::Nightly, Some(local)) => {
+            local != recipe.release.date.as_deref().expect("should have date")
         }
+        _ => false,
     };
 
-    path.pop();
-    path.pop();
-    result
+    if should_update {
+        let settings = Settings::load()?;
+        let hook_context = HookContext {
+            spec: name,
+            version: Some(&recipe.release.version),
+            date: recipe.release.date.as_deref(),
+            path: &toolchain_dir,
+        };
+
+        hooks::run(Hook::PreUpdate, &settings, hook_context)?;
+        reporter::emit(Event::UpdateStarted {
+            spec: name.to_owned(),
+            version: recipe.release.version.clone(),
+            date: recipe.release.date.clone(),
+        });
+        populate_install(&recipe).await?;
+        post_install(&recipe)?;
+        // Executables dropped by the new release leave stale shims behind
+        super::shims::reconcile()?;
+        if let Err(err) = hooks::run(Hook::PostUpdate, &settings, hook_context) {
+            hook_failures.push(err);
+        }
+    } else {
+        reporter::emit(Event::UpToDate {
+            spec: name.to_owned(),
+        });
+    }
+
+    Ok(())
 }
@@ -0,0 +1,214 @@
+use clap::Parser;
+use miette::{Context, IntoDiagnostic};
+use std::path::Path;
+
+use crate::{
+    fs::compute_file_sha256,
+    toolchain::{
+        InstalledToolchain, ToolchainSpec, installed_toolchains,
+        manifest::{Manifest, VerifyReport},
+        package::extract_components,
+    },
+};
+
+use super::ToolchainSpecValueParser;
+use super::install::post_install;
+
+/// Verify installed toolchains against their file manifest
+///
+/// Files of a toolchain are re-hashed and compared to the manifest recorded
+/// at install time, reporting modified, missing and extra files.
+#[derive(Parser, Debug)]
+pub struct Args {
+    /// The toolchain to verify, all installed toolchains by default
+    #[clap(value_parser = ToolchainSpecValueParser::new())]
+    toolchain: Option<ToolchainSpec>,
+
+    /// Restore modified and missing files from the cached downloads and
+    /// remove extra files
+    #[clap(long)]
+    repair: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let toolchains = match args.toolchain {
+        Some(spec) => {
+            let toolchain_dir = spec.install_path();
+            if !toolchain_dir.exists() {
+                return Err(miette::miette!(
+                    help = format!("run `moonup install {spec}` to install it"),
+                    "toolchain '{}' is not installed",
+                    spec
+                ));
+            }
+            vec![InstalledToolchain::from_path(&toolchain_dir)?]
+        }
+        None => installed_toolchains()?,
+    };
+
+    if toolchains.is_empty() {
+        println!("No toolchains installed");
+        return Ok(());
+    }
+
+    let mut failed = vec![];
+    for toolchain in toolchains.iter() {
+        if !verify_toolchain(toolchain, args.repair).await? {
+            failed.push(toolchain.name.to_string());
+        }
+    }
+
+    if !failed.is_empty() {
+        let help = match args.repair {
+            true => "run `moonup install <toolchain>` to reinstall the toolchain".to_owned(),
+            false => "run `moonup verify --repair` to restore the toolchain".to_owned(),
+        };
+        return Err(miette::miette!(
+            help = help,
+            "verification failed for toolchain(s): {}",
+            failed.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Verify the given toolchain, repairing it if requested
+///
+/// # Returns
+///
+/// `true` if the toolchain is intact or has been repaired
+async fn verify_toolchain(toolchain: &InstalledToolchain, repair: bool) -> miette::Result<bool> {
+    let name = &toolchain.name;
+    let toolchain_dir = name.install_path();
+
+    let Some(manifest) = Manifest::read(&toolchain_dir)? else {
+        println!(
+            "{} Skipped toolchain '{}', it was installed without a file manifest. Reinstall it with `moonup install {}` to enable verification",
+            console::style("!").yellow(),
+            name,
+            name
+        );
+        return Ok(true);
+    };
+
+    println!("Verifying toolchain '{}'", name);
+    let report = verify_dir(&manifest, &toolchain_dir).await?;
+    if report.is_intact() {
+        println!(
+            "{}Toolchain '{}' is intact ({} files)",
+            console::style(console::Emoji("✔ ", "")).green(),
+            name,
+            manifest.files.len()
+        );
+        return Ok(true);
+    }
+
+    print_report(&report);
+
+    if !repair {
+        println!(
+            "{}Toolchain '{}' has {} modified, {} missing and {} extra file(s)",
+            console::style(console::Emoji("✘ ", "")).red(),
+            name,
+            report.modified.len(),
+            report.missing.len(),
+            report.extra.len()
+        );
+        return Ok(false);
+    }
+
+    repair_toolchain(toolchain, &toolchain_dir, &report).await?;
+
+    let report = verify_dir(&manifest, &toolchain_dir).await?;
+    if !report.is_intact() {
+        print_report(&report);
+        return Ok(false);
+    }
+
+    println!(
+        "{}Repaired toolchain '{}'",
+        console::style(console::Emoji("✔ ", "")).green(),
+        name
+    );
+
+    Ok(true)
+}
+
+async fn verify_dir(manifest: &Manifest, toolchain_dir: &Path) -> miette::Result<VerifyReport> {
+    let manifest = manifest.clone();
+    let toolchain_dir = toolchain_dir.to_owned();
+    tokio::task::spawn_blocking(move || manifest.verify(&toolchain_dir))
+        .await
+        .into_diagnostic()?
+}
+
+/// Re-extract the toolchain from the cached downloads and remove extra files
+async fn repair_toolchain(
+    toolchain: &InstalledToolchain,
+    toolchain_dir: &Path,
+    report: &VerifyReport,
+) -> miette::Result<()> {
+    let name = &toolchain.name;
+    let reinstall_help = format!("run `moonup install {name}` to reinstall the toolchain");
+
+    let Some(receipt) = &toolchain.receipt else {
+        return Err(miette::miette!(
+            help = reinstall_help,
+            "cannot repair toolchain '{}' without an install receipt",
+            name
+        ));
+    };
+    let recipe = receipt.to_recipe();
+
+    // Only extract from cached downloads that are intact
+    for component in recipe.components.iter() {
+        let blob = crate::cache::blob_path(&component.sha256)?;
+        let is_intact = blob.is_file()
+            && compute_file_sha256(&blob)
+                .await
+                .is_ok_and(|sha256| format!("{:x}", sha256) == component.sha256);
+        if !is_intact {
+            return Err(miette::miette!(
+                help = reinstall_help,
+                "cached download of component '{}' is missing or corrupted",
+                component.name
+            ));
+        }
+    }
+
+    println!("Repairing toolchain '{}'", name);
+    extract_components(&recipe, toolchain_dir).await?;
+
+    for path in report.extra.iter() {
+        tracing::debug!("removing extra file {}", path);
+        std::fs::remove_file(toolchain_dir.join(path))
+            .into_diagnostic()
+            .wrap_err(format!("failed to remove {}", path))?;
+    }
+
+    // The bundled core library is stale once its sources have changed
+    let is_core_changed = report
+        .modified
+        .iter()
+        .chain(report.missing.iter())
+        .chain(report.extra.iter())
+        .any(|path| path.starts_with("lib/core/"));
+    if is_core_changed {
+        post_install(&recipe)?;
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &VerifyReport) {
+    for path in report.modified.iter() {
+        println!("  {}  {}", console::style("modified").yellow(), path);
+    }
+    for path in report.missing.iter() {
+        println!("  {}   {}", console::style("missing").red(), path);
+    }
+    for path in report.extra.iter() {
+        println!("  {}     {}", console::style("extra").cyan(), path);
+    }
+}
@@ -1,34 +1,199 @@
 use clap::Parser;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::process::Command;
 
 use crate::{
-    runner,
-    toolchain::{ToolchainSpec, resolve::detect_active_toolchainspec},
+    runner::{self, CommandNotFound},
+    toolchain::{ToolchainSpec, installed_toolchains, resolve::detect_active_toolchainspec},
 };
 
+use super::ToolchainSpecValueParser;
+
 /// Show the actual binary that will be run for a given command
 #[derive(Parser, Debug)]
 #[clap(arg_required_else_help = true)]
 pub struct Args {
     /// The command to inspect
     command: String,
+
+    /// The toolchain to look up the command in, the active one by default
+    #[clap(long, value_parser = ToolchainSpecValueParser::new(), conflicts_with = "all")]
+    toolchain: Option<ToolchainSpec>,
+
+    /// Show the command of every installed toolchain
+    #[clap(long)]
+    all: bool,
+
+    /// Show the environment variables set for the command
+    #[clap(long)]
+    env: bool,
+
+    /// Print the resolution as JSON
+    #[clap(long)]
+    json: bool,
 }
 
-fn format_command(cmd: &std::process::Command) -> String {
-    let mut parts = vec![cmd.get_program().to_string_lossy().into_owned()];
-    parts.extend(cmd.get_args().map(|arg| arg.to_string_lossy().into_owned()));
-    parts.join(" ")
+/// How a command resolves in a toolchain
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Resolution {
+    toolchain: String,
+    command: String,
+    found: bool,
+    program: Option<String>,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    available_in: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip)]
+    not_found: Option<CommandNotFound>,
+}
+
+impl Resolution {
+    /// Resolve the command in the toolchain
+    ///
+    /// A command missing from the toolchain is a resolution, any other
+    /// failure, e.g. the toolchain is not installed, is an error.
+    fn new(toolchain: ToolchainSpec, command: &str) -> miette::Result<Self> {
+        let mut resolution = Self::unresolved(&toolchain, command);
+
+        match runner::build_command(toolchain, vec![command]) {
+            Ok(cmd) => {
+                resolution.found = true;
+                resolution.program = Some(cmd.get_program().to_string_lossy().into_owned());
+                resolution.args = cmd
+                    .get_args()
+                    .map(|arg| arg.to_string_lossy().into_owned())
+                    .collect();
+                resolution.env = envs(&cmd);
+            }
+            Err(err) => match err.downcast::<CommandNotFound>() {
+                Ok(not_found) => {
+                    resolution.available_in = not_found
+                        .available_in
+                        .iter()
+                        .map(ToolchainSpec::to_string)
+                        .collect();
+                    resolution.not_found = Some(not_found);
+                }
+                Err(err) => return Err(miette::miette!(err.to_string())),
+            },
+        }
+
+        Ok(resolution)
+    }
+
+    /// A resolution recording nothing about the command yet
+    fn unresolved(toolchain: &ToolchainSpec, command: &str) -> Self {
+        Self {
+            toolchain: toolchain.to_string(),
+            command: command.to_owned(),
+            found: false,
+            program: None,
+            args: vec![],
+            env: BTreeMap::new(),
+            available_in: vec![],
+            error: None,
+            not_found: None,
+        }
+    }
+
+    /// The error for a command missing from the toolchain
+    fn not_found_error(&self) -> Option<miette::Report> {
+        let not_found = self.not_found.as_ref()?;
+        let mut diagnostic = miette::MietteDiagnostic::new(format!(
+            "Command '{}' not found in toolchain '{}'",
+            not_found.command, not_found.toolchain
+        ));
+        if let Some(hint) = not_found.hint() {
+            diagnostic = diagnostic.with_help(hint);
+        }
+        Some(diagnostic.into())
+    }
+
+    fn command_line(&self) -> Option<String> {
+        let program = self.program.as_ref()?;
+        let mut parts = vec![program.clone()];
+        parts.extend(self.args.iter().cloned());
+        Some(parts.join(" "))
+    }
+}
+
+/// The environment variables set for the command
+fn envs(cmd: &Command) -> BTreeMap<String, String> {
+    cmd.get_envs()
+        .filter_map(|(key, value)| {
+            Some((
+                key.to_string_lossy().into_owned(),
+                value?.to_string_lossy().into_owned(),
+            ))
+        })
+        .collect()
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
-    let active_toolchain = ToolchainSpec::from(detect_active_toolchainspec());
+    if args.all {
+        let resolutions = installed_toolchains()?
+            .into_iter()
+            .map(|t| {
+                Resolution::new(t.name.clone(), &args.command).unwrap_or_else(|err| {
+                    // Keep listing the other toolchains
+                    let mut resolution = Resolution::unresolved(&t.name, &args.command);
+                    resolution.error = Some(err.to_string());
+                    resolution
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if args.json {
+            let json = serde_json::to_string_pretty(&resolutions).into_diagnostic()?;
+            println!("{}", json);
+            return Ok(());
+        }
+
+        if resolutions.is_empty() {
+            eprintln!("No toolchains installed");
+        }
+        for resolution in resolutions.iter() {
+            match (resolution.command_line(), &resolution.error) {
+                (Some(line), _) => println!("{}: {}", resolution.toolchain, line),
+                (None, Some(error)) => println!("{}: {}", resolution.toolchain, error),
+                (None, None) => println!("{}: not found", resolution.toolchain),
+            }
+            if args.env {
+                print_env(resolution, "  ");
+            }
+        }
+        return Ok(());
+    }
+
+    let toolchain = args
+        .toolchain
+        .unwrap_or_else(|| ToolchainSpec::from(detect_active_toolchainspec()));
+    let resolution = Resolution::new(toolchain, &args.command)?;
 
-    match runner::build_command(active_toolchain, vec![args.command.as_str()]) {
-        Ok(cmd) => println!("{}", format_command(&cmd)),
-        Err(err) if err.to_string().starts_with("Command '") => {
-            eprintln!("No command found for '{}'", args.command)
+    if args.json {
+        let json = serde_json::to_string_pretty(&resolution).into_diagnostic()?;
+        println!("{}", json);
+    } else if let Some(line) = resolution.command_line() {
+        println!("{}", line);
+        if args.env {
+            print_env(&resolution, "");
         }
-        Err(err) => return Err(miette::miette!(err.to_string())),
     }
 
-    Ok(())
+    match resolution.not_found_error() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+fn print_env(resolution: &Resolution, indent: &str) {
+    for (key, value) in resolution.env.iter() {
+        println!("{}{}={}", indent, key, value);
+    }
 }
@@ -13,6 +13,50 @@ pub const ENVNAME_MOONUP_HOME: &str = "MOONUP_HOME";
 /// The environment variable name for customizing the MoonUp distribution server
 pub const ENVNAME_MOONUP_DIST_SERVER: &str = "MOONUP_DIST_SERVER";
 
+/// The environment variable name for the proxy of all requests
+pub const ENVNAME_MOONUP_PROXY: &str = "MOONUP_PROXY";
+
+/// The environment variable name for the hosts bypassing the proxy
+pub const ENVNAME_MOONUP_NO_PROXY: &str = "MOONUP_NO_PROXY";
+
+/// The environment variable name for the path of extra CA certificates
+pub const ENVNAME_MOONUP_CA_BUNDLE: &str = "MOONUP_CA_BUNDLE";
+
+/// The environment variable name for trusting the system root certificates
+pub const ENVNAME_MOONUP_SYSTEM_ROOTS: &str = "MOONUP_SYSTEM_ROOTS";
+
+/// The environment variable name for the path of the TLS client certificate
+pub const ENVNAME_MOONUP_CLIENT_CERT: &str = "MOONUP_CLIENT_CERT";
+
+/// The environment variable name for the path of the TLS client key
+pub const ENVNAME_MOONUP_CLIENT_KEY: &str = "MOONUP_CLIENT_KEY";
+
+/// The environment variable name for the bearer token of the distribution
+/// server
+pub const ENVNAME_MOONUP_DIST_SERVER_TOKEN: &str = "MOONUP_DIST_SERVER_TOKEN";
+
+/// The environment variable name for the path of the netrc file
+pub const ENVNAME_NETRC: &str = "NETRC";
+
+/// The environment variable name for the connect timeout of requests
+pub const ENVNAME_MOONUP_CONNECT_TIMEOUT: &str = "MOONUP_CONNECT_TIMEOUT";
+
+/// The environment variable name for the read timeout of requests
+pub const ENVNAME_MOONUP_READ_TIMEOUT: &str = "MOONUP_READ_TIMEOUT";
+
+/// The environment variable name for the number of retries of failed requests
+pub const ENVNAME_MOONUP_RETRIES: &str = "MOONUP_RETRIES";
+
+/// The environment variable name for the minimum delay between retries
+pub const ENVNAME_MOONUP_RETRY_MIN_DELAY: &str = "MOONUP_RETRY_MIN_DELAY";
+
+/// The environment variable name for the maximum delay between retries
+pub const ENVNAME_MOONUP_RETRY_MAX_DELAY: &str = "MOONUP_RETRY_MAX_DELAY";
+
+/// The environment variable name for the download rate limit in bytes per
+/// second
+pub const ENVNAME_MOONUP_DOWNLOAD_RATE_LIMIT: &str = "MOONUP_DOWNLOAD_RATE_LIMIT";
+
 /// The URL of the MoonUp distribution server
 pub const MOONUP_DIST_SERVER: &str = "https://moonup.csu.moe/v3";
 
@@ -22,9 +66,53 @@ pub const INDEX_EXPIRATION: i64 = 1;
 /// The timeout for reading HTTP responses, in seconds
 pub const HTTP_READ_TIMEOUT: u64 = 5 * 60;
 
+/// The timeout for connecting to HTTP servers, in seconds
+pub const HTTP_CONNECT_TIMEOUT: u64 = 30;
+
+/// The number of retries of failed HTTP requests
+pub const HTTP_RETRIES: u32 = 3;
+
+/// The environment variable name for the toolchain spec spread to child
+/// shim processes
+pub const ENVNAME_MOONUP_TOOLCHAIN_SPEC: &str = "MOONUP_TOOLCHAIN_SPEC";
+
+/// The environment variable name for the auto-install policy of the shims
+pub const ENVNAME_MOONUP_AUTO_INSTALL: &str = "MOONUP_AUTO_INSTALL";
+
+/// The file in `MOONUP_HOME` recording the path of the moonup binary that
+/// poured the shims
+pub const MOONUP_EXE_FILE: &str = "moonup-exe";
+
+/// The prefix of the version marker embedded in `moonup-shim`, followed by
+/// the version and a NUL byte
+pub const SHIM_VERSION_MARKER: &str = "moonup-shim-version:";
+
+/// The environment variable name for how the shims are poured, one of
+/// `copy`, `symlink` and `hardlink`
+pub const ENVNAME_MOONUP_SHIM_MODE: &str = "MOONUP_SHIM_MODE";
+
+/// The file in `MOONUP_HOME` listing the shims poured into `MOON_HOME`
+pub const SHIMS_FILE: &str = "shims";
+
+/// The log file of the shims in the `logs` directory of `MOONUP_HOME`
+pub const SHIM_LOG_FILE: &str = "shim.log";
+
 /// The filename for specifying the toolchain version
 pub const TOOLCHAIN_FILE: &str = "moonbit-version";
 
+/// The filename of the user settings in the MoonUp home directory
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// The filename of the install receipt in a toolchain directory
+pub const TOOLCHAIN_RECEIPT_FILE: &str = "receipt.json";
+
+/// The filename of the file manifest in a toolchain directory
+pub const TOOLCHAIN_MANIFEST_FILE: &str = "manifest.json";
+
+/// The filename of the marker of an incomplete install in a toolchain
+/// directory, it holds the output of the failed core library bundling
+pub const TOOLCHAIN_INCOMPLETE_FILE: &str = "incomplete";
+
 /// The maximum number of recursions allowed
 pub const RECURSION_LIMIT: u8 = 20;
 
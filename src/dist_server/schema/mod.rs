@@ -1,4 +1,4 @@
-use serde::{Deserialize, de::Error};
+use serde::{Deserialize, Serialize, de::Error};
 
 mod legacy;
 mod v2;
@@ -156,7 +156,7 @@ pub enum ComponentIndex {
 }
 
 /// Represents a component in the component index
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Component {
     /// The component name
     pub name: String,
@@ -164,6 +164,11 @@ pub struct Component {
     pub file: String,
     /// The sha256 checksum of the component
     pub sha256: String,
+    /// The archive format of the component file, e.g. `tar.gz`, `tar.xz`,
+    /// `tar.zst` or `zip`. The format is detected from the file content if
+    /// not declared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
 }
 
 impl ComponentIndex {
@@ -234,7 +239,7 @@ impl ChannelIndex {
 }
 
 /// Represents a release in the channel index
-#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Release {
     /// The (compiler) version number of the release
@@ -274,7 +279,7 @@ impl Release {
 }
 
 /// The target architecture of the toolchain
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[non_exhaustive]
 pub enum Target {
     /// Apple macOS on ARM64 (Apple Silicon)
@@ -39,6 +39,55 @@ pub fn empty_dir<P: AsRef<Path> + ?Sized>(path: &P) -> io::Result<()> {
     }
 }
 
+/// Compute the total size of the files in given `path` recursively.
+///
+/// Symlinks are not followed.
+pub fn dir_size<P: AsRef<Path>>(path: P) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in path.as_ref().read_dir()?.filter_map(io::Result::ok) {
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(entry.path())?;
+        } else {
+            size += entry.metadata()?.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Move all files and subdirectories in `src` into `dest`, replacing
+/// existing files.
+///
+/// Other files in `dest` are left alone, `src` is left with empty
+/// directories.
+pub fn merge_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dest: Q) -> io::Result<()> {
+    let dest = dest.as_ref();
+    std::fs::create_dir_all(dest)?;
+
+    for entry in src.as_ref().read_dir()?.filter_map(io::Result::ok) {
+        let target = dest.join(entry.file_name());
+        let target_type = std::fs::symlink_metadata(&target).map(|m| m.file_type());
+
+        if entry.file_type()?.is_dir() {
+            if target_type.is_ok_and(|t| !t.is_dir()) {
+                std::fs::remove_file(&target)?;
+            }
+            merge_dir(entry.path(), &target)?;
+            continue;
+        }
+
+        match target_type {
+            Ok(t) if t.is_dir() => remove_dir_all(&target)?,
+            Ok(_) => std::fs::remove_file(&target)?,
+            Err(_) => {}
+        }
+        std::fs::rename(entry.path(), &target)?;
+    }
+
+    Ok(())
+}
+
 fn save_file_sync(stream: impl Read, destination: &Path) -> miette::Result<Sha256Hash> {
     std::fs::create_dir_all(destination.parent().expect("invalid destination"))
         .into_diagnostic()?;
@@ -0,0 +1,151 @@
+use miette::IntoDiagnostic;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::reporter::{self, Event, MessageFormat};
+use crate::settings::Settings;
+
+/// The point of a toolchain operation a hook runs at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    PreInstall,
+    PostInstall,
+    PreUpdate,
+    PostUpdate,
+    PreUninstall,
+    PostUninstall,
+}
+
+impl Hook {
+    fn command<'a>(&self, settings: &'a Settings) -> Option<&'a str> {
+        let hooks = &settings.hooks;
+        match self {
+            Hook::PreInstall => hooks.pre_install.as_deref(),
+            Hook::PostInstall => hooks.post_install.as_deref(),
+            Hook::PreUpdate => hooks.pre_update.as_deref(),
+            Hook::PostUpdate => hooks.post_update.as_deref(),
+            Hook::PreUninstall => hooks.pre_uninstall.as_deref(),
+            Hook::PostUninstall => hooks.post_uninstall.as_deref(),
+        }
+    }
+
+    fn is_pre(&self) -> bool {
+        matches!(
+            self,
+            Hook::PreInstall | Hook::PreUpdate | Hook::PreUninstall
+        )
+    }
+}
+
+impl std::fmt::Display for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Hook::PreInstall => "pre-install",
+            Hook::PostInstall => "post-install",
+            Hook::PreUpdate => "pre-update",
+            Hook::PostUpdate => "post-update",
+            Hook::PreUninstall => "pre-uninstall",
+            Hook::PostUninstall => "post-uninstall",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The toolchain a hook runs for, passed to the hook as environment
+/// variables
+#[derive(Debug, Clone, Copy)]
+pub struct HookContext<'a> {
+    /// `MOONUP_HOOK_TOOLCHAIN`, the toolchain spec
+    pub spec: &'a str,
+
+    /// `MOONUP_HOOK_VERSION`, the resolved compiler version
+    pub version: Option<&'a str>,
+
+    /// `MOONUP_HOOK_DATE`, the build date of nightly toolchains
+    pub date: Option<&'a str>,
+
+    /// `MOONUP_HOOK_PATH`, the install path of the toolchain
+    pub path: &'a Path,
+}
+
+/// Run the configured command of the given hook, if any
+///
+/// A pre-hook failure aborts the operation, a post-hook failure is reported
+/// after the operation has completed.
+pub fn run(hook: Hook, settings: &Settings, context: HookContext) -> miette::Result<()> {
+    let Some(command) = hook.command(settings) else {
+        return Ok(());
+    };
+
+    reporter::emit(Event::HookStarted {
+        hook: hook.to_string(),
+        command: command.to_owned(),
+    });
+
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    cmd.env("MOONUP_HOOK", hook.to_string());
+    cmd.env("MOONUP_HOOK_TOOLCHAIN", context.spec);
+    cmd.env("MOONUP_HOOK_VERSION", context.version.unwrap_or_default());
+    cmd.env("MOONUP_HOOK_DATE", context.date.unwrap_or_default());
+    cmd.env("MOONUP_HOOK_PATH", context.path);
+
+    // Keep stdout for the events in JSON mode
+    if reporter::message_format() == MessageFormat::Json {
+        cmd.stdout(Stdio::from(std::io::stderr()));
+    }
+
+    tracing::debug!("running {} hook: {:?}", hook, cmd);
+    let status = cmd.status().into_diagnostic()?;
+    if status.success() {
+        return Ok(());
+    }
+
+    let help = match hook.is_pre() {
+        true => format!(
+            "the operation was aborted, fix or remove the hook in {}",
+            Settings::path().display()
+        ),
+        false => format!(
+            "the operation on toolchain '{}' has completed, fix or remove the hook in {}",
+            context.spec,
+            Settings::path().display()
+        ),
+    };
+    Err(miette::miette!(
+        help = help,
+        "{} hook `{}` failed ({})",
+        hook,
+        command,
+        status
+    ))
+}
+
+/// Turn the failures of post-hooks into a single error
+///
+/// Post-hooks run after their operation has completed, a failure must not
+/// skip the remaining toolchains, so failures are collected and reported at
+/// the end.
+pub fn check_failures(mut failures: Vec<miette::Report>) -> miette::Result<()> {
+    match failures.len() {
+        0 => Ok(()),
+        1 => Err(failures.remove(0)),
+        n => Err(miette::miette!(
+            help = format!("fix or remove the hooks in {}", Settings::path().display()),
+            "{} hooks failed:\n  {}",
+            n,
+            failures
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("\n  ")
+        )),
+    }
+}
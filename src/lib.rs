@@ -1,12 +1,18 @@
 use std::path::PathBuf;
 
 pub mod archive;
+pub(crate) mod auth;
+pub mod cache;
 pub mod cli;
 pub mod constant;
 pub mod dist_server;
 pub mod fs;
+pub mod hooks;
 pub(crate) mod reporter;
+pub(crate) mod retry;
 pub mod runner;
+pub mod settings;
+pub mod shim;
 pub mod toolchain;
 pub mod utils;
 
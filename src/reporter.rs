@@ -1,18 +1,274 @@
 use indicatif::ProgressBar;
+use serde::Serialize;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, ReadBuf};
+
+static MESSAGE_FORMAT: OnceLock<MessageFormat> = OnceLock::new();
+
+/// The format of the messages printed to stdout
+///
+/// `human` prints progress bars and human-readable lines, `json` prints
+/// newline-delimited JSON events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MessageFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Set the message format for the current process
+///
+/// Only the first call takes effect.
+pub fn set_message_format(format: MessageFormat) {
+    let _ = MESSAGE_FORMAT.set(format);
+}
+
+/// Get the message format of the current process
+pub fn message_format() -> MessageFormat {
+    MESSAGE_FORMAT.get().copied().unwrap_or_default()
+}
+
+/// An event reported by install, update, uninstall and self-update
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A toolchain spec has been resolved and is about to be installed
+    InstallStarted {
+        spec: String,
+        version: String,
+        date: Option<String>,
+    },
+
+    /// A toolchain has been installed
+    Installed { spec: String },
+
+    /// An installed toolchain is about to be updated
+    UpdateStarted {
+        spec: String,
+        version: String,
+        date: Option<String>,
+    },
+
+    /// An installed toolchain is already up to date
+    UpToDate { spec: String },
+
+    /// A toolchain to update is not installed
+    NotInstalled { spec: String },
+
+    /// A toolchain has been uninstalled
+    Uninstalled { spec: String },
+
+    /// All cached downloads have been removed
+    CacheCleared,
+
+    /// A stage of a subject, e.g. downloading a component, has started
+    StageStarted { subject: String, stage: Stage },
+
+    /// A status message of the current stage of a subject
+    StageMessage {
+        subject: String,
+        stage: Option<Stage>,
+        message: String,
+    },
+
+    /// Progress of the current stage of a subject in bytes
+    Progress {
+        subject: String,
+        stage: Option<Stage>,
+        current: u64,
+        total: Option<u64>,
+    },
+
+    /// The current stage of a subject has finished
+    StageFinished { subject: String, stage: Stage },
+
+    /// The checksum of a component download has been verified
+    ChecksumVerified { component: String, sha256: String },
+
+    /// Shims have been poured into the `MOON_HOME` bin directory
+    ShimsPoured { shims: Vec<String> },
+
+    /// Shims have been reconciled with the installed toolchains
+    ShimsRebuilt {
+        created: Vec<String>,
+        refreshed: Vec<String>,
+        removed: Vec<String>,
+    },
+
+    /// The core library has been bundled
+    Bundled { success: bool, status: String },
+
+    /// A user-defined hook is about to run
+    HookStarted { hook: String, command: String },
+
+    /// moonup is about to be updated
+    SelfUpdateStarted { from: String, to: String },
+
+    /// moonup has been updated
+    SelfUpdated { version: String },
+
+    /// moonup is already the latest version
+    SelfUpToDate { version: String },
+
+    /// An informational message
+    Message { message: String },
+
+    /// The command failed
+    Error { message: String },
+}
+
+impl Event {
+    /// The human-readable line of the event, if any
+    fn human(&self) -> Option<String> {
+        let ok = console::style(console::Emoji("✔ ", "")).green();
+        match self {
+            Event::InstallStarted { spec, .. } => Some(format!("Installing toolchain '{}'", spec)),
+            Event::Installed { spec } => {
+                Some(format!("{}Installed toolchain version '{}'", ok, spec))
+            }
+            Event::UpdateStarted { spec, .. } => Some(format!("Updating the {} toolchain", spec)),
+            Event::UpToDate { spec } => Some(format!("The {} toolchain is up to date", spec)),
+            Event::NotInstalled { spec } => Some(format!(
+                "{spec} toolchain is not installed, run 'moonup install {spec}' to install"
+            )),
+            Event::Uninstalled { spec } => Some(format!(
+                "{} Uninstalled toolchain {}",
+                ok,
+                console::style(spec).yellow().bright()
+            )),
+            Event::CacheCleared => Some(format!("{} Cleared all cached downloads", ok)),
+            Event::ShimsRebuilt {
+                created,
+                refreshed,
+                removed,
+            } => Some(match created.len() + refreshed.len() + removed.len() {
+                0 => format!("{}Shims are up to date", ok),
+                _ => format!(
+                    "{}Rebuilt shims: {} created, {} refreshed, {} removed",
+                    ok,
+                    created.len(),
+                    refreshed.len(),
+                    removed.len()
+                ),
+            }),
+            Event::Bundled { success: true, .. } => Some(format!("{}Bundled core library", ok)),
+            Event::Bundled { status, .. } => Some(format!(
+                "{} Failed to bundle core library ({})",
+                console::style("!").yellow(),
+                status
+            )),
+            Event::SelfUpdateStarted { from, to } => {
+                Some(format!("Updating moonup: {} -> {}", from, to))
+            }
+            Event::HookStarted { hook, command } => {
+                Some(format!("Running {} hook: {}", hook, command))
+            }
+            Event::SelfUpToDate { .. } => Some("moonup is already up-to-date".to_owned()),
+            Event::Message { message } => Some(message.clone()),
+            // Progress is rendered by the progress bars, and errors are
+            // rendered by the error handler
+            _ => None,
+        }
+    }
+}
+
+/// Report an event to stdout in the current message format
+pub fn emit(event: Event) {
+    match message_format() {
+        MessageFormat::Human => {
+            if let Some(line) = event.human() {
+                println!("{}", line);
+            }
+        }
+        MessageFormat::Json => match serde_json::to_string(&event) {
+            Ok(line) => println!("{}", line),
+            Err(e) => tracing::warn!("failed to serialize event: {}", e),
+        },
+    }
+}
+
+/// Create a reporter for the given subject in the current message format
+pub fn reporter(subject: &str) -> Arc<dyn Reporter> {
+    match message_format() {
+        MessageFormat::Human => Arc::new(ProgressReporter::new(subject.to_owned())),
+        MessageFormat::Json => Arc::new(JsonReporter::new(subject.to_owned())),
+    }
+}
+
+/// The stage of an install step being reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Downloading a file, progress is reported in bytes
+    Download,
+
+    /// Verifying the checksum of a file
+    Verify,
+
+    /// Extracting an archive, progress is reported in bytes of the archive
+    Extract,
+
+    /// Bundling the core library
+    Bundle,
+}
+
+impl Stage {
+    fn verb(&self) -> &'static str {
+        match self {
+            Stage::Download => "Downloading",
+            Stage::Verify => "Verifying",
+            Stage::Extract => "Extracting",
+            Stage::Bundle => "Bundling",
+        }
+    }
+}
 
 pub struct ProgressReporter {
     progress_bar: ProgressBar,
+    subject: String,
 }
 
 impl ProgressReporter {
-    pub fn new(prefix: String) -> Self {
-        let progress_bar = ProgressBar::new(1).with_style(download_style());
-        progress_bar.set_prefix(prefix);
-        Self { progress_bar }
+    /// Create a reporter for the given subject, e.g. a component name,
+    /// starting at the download stage
+    pub fn new(subject: String) -> Self {
+        let progress_bar = ProgressBar::new(1);
+        let reporter = Self {
+            progress_bar,
+            subject,
+        };
+        reporter.on_stage(Stage::Download);
+        reporter
     }
 }
 
 impl Reporter for ProgressReporter {
+    fn on_stage(&self, stage: Stage) {
+        let style = match stage {
+            Stage::Download | Stage::Extract => bytes_style(),
+            Stage::Verify | Stage::Bundle => spinner_style(),
+        };
+        self.progress_bar.set_style(style);
+        self.progress_bar
+            .set_prefix(format!("{} {}", stage.verb(), self.subject));
+        self.progress_bar.set_message("");
+
+        match stage {
+            Stage::Verify | Stage::Bundle => {
+                self.progress_bar
+                    .enable_steady_tick(Duration::from_millis(100));
+            }
+            Stage::Download | Stage::Extract => self.progress_bar.disable_steady_tick(),
+        }
+    }
+
+    fn on_message(&self, message: &str) {
+        self.progress_bar.set_message(message.to_owned());
+    }
+
     fn on_start(&self, total: usize) {
         self.progress_bar.set_length(total as u64);
         self.progress_bar.set_position(0);
@@ -27,7 +283,102 @@ impl Reporter for ProgressReporter {
     }
 }
 
+/// A reporter emitting JSON events
+///
+/// Progress events are throttled to one per percent, or one per MiB when the
+/// total size is unknown.
+pub struct JsonReporter {
+    subject: String,
+    state: Mutex<JsonReporterState>,
+}
+
+#[derive(Default)]
+struct JsonReporterState {
+    stage: Option<Stage>,
+    total: Option<u64>,
+    last_step: Option<u64>,
+}
+
+impl JsonReporter {
+    /// Create a reporter for the given subject, e.g. a component name
+    pub fn new(subject: String) -> Self {
+        Self {
+            subject,
+            state: Mutex::default(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, JsonReporterState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn on_stage(&self, stage: Stage) {
+        *self.state() = JsonReporterState {
+            stage: Some(stage),
+            ..Default::default()
+        };
+        emit(Event::StageStarted {
+            subject: self.subject.clone(),
+            stage,
+        });
+    }
+
+    fn on_message(&self, message: &str) {
+        let stage = self.state().stage;
+        emit(Event::StageMessage {
+            subject: self.subject.clone(),
+            stage,
+            message: message.to_owned(),
+        });
+    }
+
+    fn on_start(&self, total: usize) {
+        let mut state = self.state();
+        state.total = (total > 0).then_some(total as u64);
+        state.last_step = None;
+    }
+
+    fn on_progress(&self, current: usize) {
+        let current = current as u64;
+        let mut state = self.state();
+        let step = match state.total {
+            Some(total) => current * 100 / total.max(1),
+            None => current >> 20,
+        };
+        if state.last_step == Some(step) {
+            return;
+        }
+        state.last_step = Some(step);
+
+        let (stage, total) = (state.stage, state.total);
+        drop(state);
+        emit(Event::Progress {
+            subject: self.subject.clone(),
+            stage,
+            current,
+            total,
+        });
+    }
+
+    fn on_complete(&self) {
+        if let Some(stage) = self.state().stage.take() {
+            emit(Event::StageFinished {
+                subject: self.subject.clone(),
+                stage,
+            });
+        }
+    }
+}
+
 pub trait Reporter: Send + Sync {
+    /// Called when a new stage starts
+    fn on_stage(&self, _stage: Stage) {}
+
+    /// Called with a status message of the current stage
+    fn on_message(&self, _message: &str) {}
+
     fn on_start(&self, total: usize);
 
     fn on_progress(&self, current: usize);
@@ -35,9 +386,53 @@ pub trait Reporter: Send + Sync {
     fn on_complete(&self);
 }
 
-fn download_style() -> indicatif::ProgressStyle {
+/// An async reader reporting the number of bytes read
+pub struct ProgressReader<R> {
+    inner: R,
+    reporter: Arc<dyn Reporter>,
+    current: usize,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, reporter: Arc<dyn Reporter>) -> Self {
+        Self {
+            inner,
+            reporter,
+            current: 0,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(())) = poll {
+            let read = buf.filled().len() - filled;
+            if read > 0 {
+                self.current += read;
+                self.reporter.on_progress(self.current);
+            }
+        }
+
+        poll
+    }
+}
+
+fn bytes_style() -> indicatif::ProgressStyle {
     indicatif::ProgressStyle::default_bar()
-        .template("{spinner:.dim} {prefix:21!} [{elapsed_precise}] [{bar:20!}] {bytes:>8}")
+        .template("{spinner:.dim} {prefix:21!} [{elapsed_precise}] [{bar:20!}] {bytes:>8} {msg}")
         .unwrap()
         .progress_chars("#> ")
 }
+
+fn spinner_style() -> indicatif::ProgressStyle {
+    indicatif::ProgressStyle::default_spinner()
+        .template("{spinner:.dim} {prefix:21!} [{elapsed_precise}] {msg}")
+        .unwrap()
+}
@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use http::Extensions;
+use reqwest::{Request, Response, StatusCode, header::RETRY_AFTER};
+use reqwest_middleware::{Error, Middleware, Next};
+use reqwest_retry::{
+    RetryDecision, RetryPolicy, Retryable, default_on_request_failure, default_on_request_success,
+    policies::ExponentialBackoff,
+};
+use std::time::{Duration, SystemTime};
+
+/// A middleware retrying transient failures with exponential backoff
+///
+/// Unlike `reqwest_retry::RetryTransientMiddleware`, the `Retry-After` header
+/// of `429 Too Many Requests` and `503 Service Unavailable` responses is
+/// honored, capped at the maximum delay.
+pub(crate) struct RetryMiddleware {
+    policy: ExponentialBackoff,
+    max_delay: Duration,
+}
+
+impl RetryMiddleware {
+    pub(crate) fn new(retries: u32, min_delay: Duration, max_delay: Duration) -> Self {
+        let policy = ExponentialBackoff::builder()
+            .retry_bounds(min_delay, max_delay)
+            .build_with_max_retries(retries);
+        Self { policy, max_delay }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for RetryMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let start_time = SystemTime::now();
+        let mut retries = 0;
+        loop {
+            let request = req.try_clone().ok_or_else(|| {
+                Error::middleware(std::io::Error::other(
+                    "streaming requests cannot be retried",
+                ))
+            })?;
+            let result = next.clone().run(request, extensions).await;
+
+            let retryable = match &result {
+                Ok(response) => default_on_request_success(response),
+                Err(e) => default_on_request_failure(e),
+            };
+            if retryable == Some(Retryable::Transient)
+                && let RetryDecision::Retry { execute_after } =
+                    self.policy.should_retry(start_time, retries)
+            {
+                let backoff = execute_after
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default();
+                let delay = match result.as_ref().ok().and_then(retry_after) {
+                    Some(delay) => delay.min(self.max_delay),
+                    None => backoff,
+                };
+
+                retries += 1;
+                tracing::debug!("retry #{} in {:?}", retries, delay);
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            break match retries {
+                0 => result,
+                n => result.map_err(|e| {
+                    Error::Middleware(anyhow::Error::new(e).context(format!(
+                        "request failed after {} {}",
+                        n,
+                        if n == 1 { "retry" } else { "retries" }
+                    )))
+                }),
+            };
+        }
+    }
+}
+
+/// Get the delay requested by the `Retry-After` header of the response
+///
+/// Both delay seconds and HTTP dates are supported.
+fn retry_after(response: &Response) -> Option<Duration> {
+    if !matches!(
+        response.status(),
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+    ) {
+        return None;
+    }
+
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
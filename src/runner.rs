@@ -1,9 +1,56 @@
 use std::env;
-use std::ffi::OsStr;
-use std::process::Command;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::process::{Command, ExitStatus};
 
 use crate::toolchain::{ToolchainSpec, resolve};
 
+/// The command is not provided by the toolchain
+#[derive(Debug, Clone)]
+pub struct CommandNotFound {
+    /// The name of the command
+    pub command: String,
+
+    /// The toolchain the command was looked up in
+    pub toolchain: ToolchainSpec,
+
+    /// Other installed toolchains providing the command
+    pub available_in: Vec<ToolchainSpec>,
+}
+
+impl CommandNotFound {
+    /// A hint pointing at the other toolchains providing the command, if any
+    pub fn hint(&self) -> Option<String> {
+        let first = self.available_in.first()?;
+        let toolchains = self
+            .available_in
+            .iter()
+            .map(ToolchainSpec::to_string)
+            .collect::<Vec<_>>();
+        Some(format!(
+            "{command} is available in {}; try `moonup run {first} {command}` or `{command} +{first}`",
+            toolchains.join(", "),
+            command = self.command,
+        ))
+    }
+}
+
+impl std::fmt::Display for CommandNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Command '{}' not found in toolchain '{}'",
+            self.command, self.toolchain
+        )?;
+        if let Some(hint) = self.hint() {
+            write!(f, "\n{hint}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CommandNotFound {}
+
 pub fn build_command<S: AsRef<OsStr>>(
     toolchain: ToolchainSpec,
     command: Vec<S>,
@@ -17,15 +64,12 @@ pub fn build_command<S: AsRef<OsStr>>(
         return Err(anyhow::anyhow!("Toolchain '{toolchain}' is not installed"));
     }
 
-    let err_msg = anyhow::anyhow!(
-        "Command '{}' not found in toolchain '{toolchain}'",
-        exe_name.to_string_lossy()
-    );
-
-    let internal_bin_dir = bin_dir.join("internal");
-
-    let paths = env::join_paths([&bin_dir, &internal_bin_dir])
-        .map_err(|e| anyhow::anyhow!("Failed to build PATH environment variable: {}", e))?;
+    let paths = toolchain_paths(&bin_dir)?;
+    let not_found = || CommandNotFound {
+        command: exe_name.to_string_lossy().into_owned(),
+        available_in: toolchains_providing(exe_name, &toolchain),
+        toolchain: toolchain.clone(),
+    };
 
     let mut cmd = if let Some(exe_resolved) = resolve::resolve_exe(exe_name, &paths) {
         tracing::debug!(
@@ -45,10 +89,7 @@ pub fn build_command<S: AsRef<OsStr>>(
         // - `moon-lsp`, version 0.9.2+bbe2b338f onwards
         // - `moonbit-lsp`, 0.6.23+906028000 ~ 0.9.1+cd5b07232
         // - `lsp-server.js`, version 0.6.22 and earlier
-        let lsp_exe = resolve::resolve_exe("moonbit-lsp", &paths)
-            .or_else(|| resolve::resolve_file("moonbit-lsp", &paths))
-            .or_else(|| resolve::resolve_file("lsp-server.js", &paths))
-            .ok_or(err_msg)?;
+        let lsp_exe = resolve_lsp_server(&paths).ok_or_else(not_found)?;
         tracing::debug!("Resolved LSP server executable: {}", lsp_exe.display());
         let runtime = resolve::resolve_exe("bun", &host_paths)
             .or_else(|| resolve::resolve_exe("node", &host_paths))
@@ -74,7 +115,7 @@ pub fn build_command<S: AsRef<OsStr>>(
 
         cmd
     } else {
-        return Err(err_msg);
+        return Err(not_found().into());
     };
 
     cmd.args(&command[1..]);
@@ -111,3 +152,93 @@ pub fn build_command<S: AsRef<OsStr>>(
     tracing::debug!("build command: {:?}", cmd);
     Ok(cmd)
 }
+
+/// The search paths of the executables of a toolchain
+fn toolchain_paths(bin_dir: &Path) -> anyhow::Result<OsString> {
+    env::join_paths([bin_dir, &bin_dir.join("internal")])
+        .map_err(|e| anyhow::anyhow!("Failed to build PATH environment variable: {}", e))
+}
+
+/// Find the JS language server of toolchains without a native `moon-lsp`
+fn resolve_lsp_server(paths: &OsStr) -> Option<std::path::PathBuf> {
+    resolve::resolve_exe("moonbit-lsp", paths)
+        .or_else(|| resolve::resolve_file("moonbit-lsp", paths))
+        .or_else(|| resolve::resolve_file("lsp-server.js", paths))
+}
+
+/// Find the installed toolchains other than `except` providing the command
+fn toolchains_providing(exe_name: &OsStr, except: &ToolchainSpec) -> Vec<ToolchainSpec> {
+    let Ok(read_dir) = crate::moonup_home().join("toolchains").read_dir() else {
+        return vec![];
+    };
+
+    let mut toolchains = read_dir
+        .filter_map(std::io::Result::ok)
+        .filter_map(|entry| {
+            let spec = ToolchainSpec::from(entry.file_name().to_str()?);
+            if &spec == except {
+                return None;
+            }
+
+            let paths = toolchain_paths(&entry.path().join("bin")).ok()?;
+            let provides = resolve::resolve_exe(exe_name, &paths).is_some()
+                || (exe_name == "moon-lsp" && resolve_lsp_server(&paths).is_some());
+            provides.then_some(spec)
+        })
+        .collect::<Vec<_>>();
+    toolchains.sort();
+    toolchains
+}
+
+/// Run the command in place of the current process
+///
+/// On Unix, the current process is replaced by the command via `exec`, so
+/// signals and the exit status reach the caller directly and this function
+/// only returns on failure. Elsewhere the command runs as a child process
+/// and its exit status is returned.
+pub fn exec(mut cmd: Command) -> anyhow::Result<ExitStatus> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        let err = cmd.exec();
+        Err(anyhow::anyhow!(
+            "Failed to execute '{}': {}",
+            cmd.get_program().to_string_lossy(),
+            err
+        ))
+    }
+
+    #[cfg(not(unix))]
+    cmd.status().map_err(anyhow::Error::from)
+}
+
+/// Exit the current process with the exit status of a child process
+///
+/// On Unix, a child terminated by a signal has no exit code, so the signal
+/// is re-raised to terminate the current process the same way.
+pub fn exit_with(status: ExitStatus) -> ! {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            // SAFETY: restoring the default disposition and raising the
+            // signal only affects the current process, which is about to
+            // exit anyway
+            unsafe {
+                let mut set = std::mem::zeroed::<libc::sigset_t>();
+                libc::sigemptyset(&mut set);
+                libc::sigaddset(&mut set, signal);
+                libc::sigprocmask(libc::SIG_UNBLOCK, &set, std::ptr::null_mut());
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+
+            // The signal doesn't terminate, exit like a shell would report it
+            std::process::exit(128 + signal);
+        }
+    }
+
+    std::process::exit(status.code().unwrap_or(1))
+}
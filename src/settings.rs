@@ -0,0 +1,317 @@
+use miette::{Context, IntoDiagnostic};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::constant::{
+    ENVNAME_MOONUP_CA_BUNDLE, ENVNAME_MOONUP_CLIENT_CERT, ENVNAME_MOONUP_CLIENT_KEY,
+    ENVNAME_MOONUP_CONNECT_TIMEOUT, ENVNAME_MOONUP_DOWNLOAD_RATE_LIMIT, ENVNAME_MOONUP_NO_PROXY,
+    ENVNAME_MOONUP_PROXY, ENVNAME_MOONUP_READ_TIMEOUT, ENVNAME_MOONUP_RETRIES,
+    ENVNAME_MOONUP_RETRY_MAX_DELAY, ENVNAME_MOONUP_RETRY_MIN_DELAY, ENVNAME_MOONUP_SYSTEM_ROOTS,
+    HTTP_CONNECT_TIMEOUT, HTTP_READ_TIMEOUT, HTTP_RETRIES, SETTINGS_FILE,
+};
+use crate::utils::{parse_duration, parse_size};
+
+/// User settings read from `MOONUP_HOME`/settings.toml
+///
+/// All settings are optional, a missing settings file is equal to an empty
+/// one.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Settings {
+    /// Commands to run before and after toolchain operations
+    pub hooks: Hooks,
+
+    /// HTTP client configuration
+    pub network: Network,
+
+    /// Credentials of private servers, keyed by `host` or `host:port`
+    pub auth: BTreeMap<String, HostAuth>,
+
+    /// Whether the shims install a missing toolchain, `always` by default
+    pub auto_install: Option<AutoInstall>,
+
+    /// How the shims are poured into `MOON_HOME`, `copy` by default
+    pub shim_mode: Option<ShimMode>,
+}
+
+/// The policy of the shims for installing a missing toolchain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoInstall {
+    /// Install the toolchain without asking
+    #[default]
+    Always,
+
+    /// Ask before installing, fail if there is no terminal to ask on
+    Prompt,
+
+    /// Fail without installing
+    Never,
+}
+
+impl std::str::FromStr for AutoInstall {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "always" => Ok(AutoInstall::Always),
+            "prompt" => Ok(AutoInstall::Prompt),
+            "never" => Ok(AutoInstall::Never),
+            _ => Err(format!(
+                "invalid auto-install policy '{s}', expected one of always, prompt, never"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for AutoInstall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AutoInstall::Always => "always",
+            AutoInstall::Prompt => "prompt",
+            AutoInstall::Never => "never",
+        })
+    }
+}
+
+/// How the shims are poured into `MOON_HOME`
+///
+/// Links are only supported on Unix, shims are always copied on Windows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShimMode {
+    /// Copy `moonup-shim` for every executable
+    #[default]
+    Copy,
+
+    /// Symlink every executable to `moonup-shim`
+    Symlink,
+
+    /// Hardlink every executable to `moonup-shim`, falls back to copies
+    /// across filesystems
+    Hardlink,
+}
+
+impl std::str::FromStr for ShimMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "copy" => Ok(ShimMode::Copy),
+            "symlink" => Ok(ShimMode::Symlink),
+            "hardlink" => Ok(ShimMode::Hardlink),
+            _ => Err(format!(
+                "invalid shim mode '{s}', expected one of copy, symlink, hardlink"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ShimMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ShimMode::Copy => "copy",
+            ShimMode::Symlink => "symlink",
+            ShimMode::Hardlink => "hardlink",
+        })
+    }
+}
+
+/// Hook commands, run by `sh -c` on Unix and `cmd /C` on Windows
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Hooks {
+    pub pre_install: Option<String>,
+    pub post_install: Option<String>,
+    pub pre_update: Option<String>,
+    pub post_update: Option<String>,
+    pub pre_uninstall: Option<String>,
+    pub post_uninstall: Option<String>,
+}
+
+/// Credentials of a private server
+///
+/// A bearer token takes precedence over the username and password.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HostAuth {
+    pub token: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl std::fmt::Debug for HostAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redacted = |v: &Option<String>| v.as_ref().map(|_| "***");
+        f.debug_struct("HostAuth")
+            .field("token", &redacted(&self.token))
+            .field("username", &self.username)
+            .field("password", &redacted(&self.password))
+            .finish()
+    }
+}
+
+/// HTTP client configuration
+///
+/// Each setting can be overridden by its `MOONUP_*` environment variable.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Network {
+    /// The proxy of all requests, e.g. `http://proxy:8080` or
+    /// `socks5h://proxy:1080`, the `HTTP(S)_PROXY` and `ALL_PROXY`
+    /// environment variables are used if not set
+    pub proxy: Option<String>,
+
+    /// Comma-separated hosts, domains and IP ranges bypassing the proxy
+    pub no_proxy: Option<String>,
+
+    /// The path of a PEM file with extra CA certificates to trust
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Whether to trust the system root certificates, only the CA bundle is
+    /// trusted if disabled
+    pub system_roots: bool,
+
+    /// The path of a PEM file with the TLS client certificate, it may contain
+    /// the private key as well
+    pub client_cert: Option<PathBuf>,
+
+    /// The path of a PEM file with the private key of the client certificate
+    pub client_key: Option<PathBuf>,
+
+    /// The timeout for connecting to a server, e.g. `30s`
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub connect_timeout: Duration,
+
+    /// The timeout for reading a response, e.g. `5m`
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub read_timeout: Duration,
+
+    /// The number of retries of failed requests, `0` disables retrying
+    pub retries: u32,
+
+    /// The delay before the first retry, doubled for every further retry
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub retry_min_delay: Duration,
+
+    /// The maximum delay between retries, also caps the `Retry-After` delay
+    /// requested by the server
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub retry_max_delay: Duration,
+
+    /// The maximum download speed in bytes per second, e.g. `2M`, unlimited
+    /// if not set or `0`
+    #[serde(deserialize_with = "deserialize_size")]
+    pub download_rate_limit: Option<u64>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            no_proxy: None,
+            ca_bundle: None,
+            system_roots: true,
+            client_cert: None,
+            client_key: None,
+            connect_timeout: Duration::from_secs(HTTP_CONNECT_TIMEOUT),
+            read_timeout: Duration::from_secs(HTTP_READ_TIMEOUT),
+            retries: HTTP_RETRIES,
+            retry_min_delay: Duration::from_secs(1),
+            retry_max_delay: Duration::from_secs(60),
+            download_rate_limit: None,
+        }
+    }
+}
+
+impl Network {
+    /// Apply the overrides from the environment variables
+    pub fn with_env(mut self) -> miette::Result<Self> {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let invalid = |name: &str, e: String| miette::miette!("invalid {}: {}", name, e);
+
+        if let Some(proxy) = var(ENVNAME_MOONUP_PROXY) {
+            self.proxy = Some(proxy);
+        }
+        if let Some(no_proxy) = var(ENVNAME_MOONUP_NO_PROXY) {
+            self.no_proxy = Some(no_proxy);
+        }
+        if let Some(path) = var(ENVNAME_MOONUP_CA_BUNDLE) {
+            self.ca_bundle = Some(PathBuf::from(path));
+        }
+        if let Some(flag) = var(ENVNAME_MOONUP_SYSTEM_ROOTS) {
+            self.system_roots = !matches!(flag.to_ascii_lowercase().as_str(), "0" | "false" | "no");
+        }
+        if let Some(path) = var(ENVNAME_MOONUP_CLIENT_CERT) {
+            self.client_cert = Some(PathBuf::from(path));
+        }
+        if let Some(path) = var(ENVNAME_MOONUP_CLIENT_KEY) {
+            self.client_key = Some(PathBuf::from(path));
+        }
+
+        for (name, field) in [
+            (ENVNAME_MOONUP_CONNECT_TIMEOUT, &mut self.connect_timeout),
+            (ENVNAME_MOONUP_READ_TIMEOUT, &mut self.read_timeout),
+            (ENVNAME_MOONUP_RETRY_MIN_DELAY, &mut self.retry_min_delay),
+            (ENVNAME_MOONUP_RETRY_MAX_DELAY, &mut self.retry_max_delay),
+        ] {
+            if let Some(value) = var(name) {
+                *field = parse_duration(&value).map_err(|e| invalid(name, e))?;
+            }
+        }
+        if let Some(retries) = var(ENVNAME_MOONUP_RETRIES) {
+            self.retries = retries
+                .parse()
+                .map_err(|_| invalid(ENVNAME_MOONUP_RETRIES, format!("'{retries}'")))?;
+        }
+        if let Some(limit) = var(ENVNAME_MOONUP_DOWNLOAD_RATE_LIMIT) {
+            let limit =
+                parse_size(&limit).map_err(|e| invalid(ENVNAME_MOONUP_DOWNLOAD_RATE_LIMIT, e))?;
+            self.download_rate_limit = Some(limit);
+        }
+
+        if self.retry_min_delay > self.retry_max_delay {
+            return Err(miette::miette!(
+                "the minimum retry delay {:?} exceeds the maximum retry delay {:?}",
+                self.retry_min_delay,
+                self.retry_max_delay
+            ));
+        }
+
+        Ok(self)
+    }
+}
+
+impl Settings {
+    /// Get the path of the settings file
+    pub fn path() -> PathBuf {
+        crate::moonup_home().join(SETTINGS_FILE)
+    }
+
+    /// Read the settings file
+    pub fn load() -> miette::Result<Self> {
+        let path = Self::path();
+        let content = match std::fs::read_to_string(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            other => other
+                .into_diagnostic()
+                .wrap_err(format!("failed to read {}", path.display()))?,
+        };
+
+        toml::from_str(&content)
+            .into_diagnostic()
+            .wrap_err(format!("malformed settings {}", path.display()))
+    }
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_duration(&s).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_size(&s).map(Some).map_err(serde::de::Error::custom)
+}
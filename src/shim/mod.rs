@@ -0,0 +1,158 @@
+pub mod pour;
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::constant::{ENVNAME_MOONUP_AUTO_INSTALL, MOONUP_EXE_FILE, SHIM_VERSION_MARKER};
+use crate::settings::{AutoInstall, Settings};
+use crate::toolchain::resolve::{ToolchainFile, resolve_toolchain_file};
+
+/// Where the auto-install policy is set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicySource {
+    Environment,
+    ToolchainFile(PathBuf),
+    Settings(PathBuf),
+    Default,
+}
+
+impl std::fmt::Display for PolicySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicySource::Environment => write!(f, "{ENVNAME_MOONUP_AUTO_INSTALL}"),
+            PolicySource::ToolchainFile(path) | PolicySource::Settings(path) => {
+                write!(f, "{}", path.display())
+            }
+            PolicySource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Detect the auto-install policy of the shims
+///
+/// The `MOONUP_AUTO_INSTALL` environment variable takes precedence over the
+/// `auto-install` option of the toolchain file, which takes precedence over
+/// the `auto-install` setting.
+pub fn auto_install_policy() -> anyhow::Result<(AutoInstall, PolicySource)> {
+    if let Some(value) = std::env::var(ENVNAME_MOONUP_AUTO_INSTALL)
+        .ok()
+        .filter(|v| !v.is_empty())
+    {
+        let policy = value
+            .parse()
+            .map_err(|e| anyhow::anyhow!("{e} in {ENVNAME_MOONUP_AUTO_INSTALL}"))?;
+        return Ok((policy, PolicySource::Environment));
+    }
+
+    if let Some(path) = resolve_toolchain_file()
+        && let Some(value) =
+            ToolchainFile::read(&path).and_then(|f| f.option("auto-install").map(str::to_owned))
+    {
+        let policy = value
+            .parse()
+            .map_err(|e| anyhow::anyhow!("{e} in {}", path.display()))?;
+        return Ok((policy, PolicySource::ToolchainFile(path)));
+    }
+
+    let settings = Settings::load().map_err(|e| {
+        let causes = e.chain().map(|c| c.to_string()).collect::<Vec<_>>();
+        anyhow::anyhow!(causes.join(": "))
+    })?;
+    Ok(match settings.auto_install {
+        Some(policy) => (policy, PolicySource::Settings(Settings::path())),
+        None => (AutoInstall::default(), PolicySource::Default),
+    })
+}
+
+/// Get the path of the `moonup-shim` executable
+///
+/// The shim is shipped alongside the `moonup` executable.
+pub fn shim_exe() -> PathBuf {
+    let args = std::env::args_os().collect::<Vec<_>>();
+    let mut shim_exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from(&args[0]));
+    shim_exe.set_file_name(format!("moonup-shim{}", std::env::consts::EXE_SUFFIX));
+    shim_exe
+}
+
+/// Record the path of the running `moonup` binary as the owner of the shims
+pub fn record_moonup_exe() -> std::io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+
+    let moonup_home = crate::moonup_home();
+    std::fs::create_dir_all(&moonup_home)?;
+    std::fs::write(
+        moonup_home.join(MOONUP_EXE_FILE),
+        exe.to_string_lossy().as_bytes(),
+    )
+}
+
+/// Locate the `moonup` binary owning the shims
+///
+/// The binary recorded when the shims were poured is preferred, then
+/// `$MOONUP_HOME/bin/moonup`, and `moonup` on PATH as a last resort.
+pub fn locate_moonup() -> PathBuf {
+    let moonup_home = crate::moonup_home();
+    let recorded = std::fs::read_to_string(moonup_home.join(MOONUP_EXE_FILE))
+        .ok()
+        .map(|path| PathBuf::from(path.trim()));
+    let exe_name = format!("moonup{}", std::env::consts::EXE_SUFFIX);
+
+    recorded
+        .into_iter()
+        .chain([moonup_home.join("bin").join(exe_name)])
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from("moonup"))
+}
+
+/// Check if the shim at `path` is an identical copy of `shim_exe`
+pub fn is_current(path: &Path, shim_exe: &Path) -> std::io::Result<bool> {
+    if path.metadata()?.len() != shim_exe.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let mut a = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut b = std::io::BufReader::new(std::fs::File::open(shim_exe)?);
+    let mut buf_a = [0u8; 8192];
+    let mut buf_b = [0u8; 8192];
+    loop {
+        let n = a.read(&mut buf_a)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buf_b[..n])?;
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Read the moonup version a shim was built with from its version marker
+///
+/// # Returns
+///
+/// The version, `None` if the shim has no version marker, e.g. it was
+/// poured by an older moonup.
+pub fn shim_version(path: &Path) -> std::io::Result<Option<String>> {
+    let content = std::fs::read(path)?;
+    let marker = SHIM_VERSION_MARKER.as_bytes();
+
+    let mut rest = content.as_slice();
+    while let Some(pos) = rest.windows(marker.len()).position(|w| w == marker) {
+        rest = &rest[pos + marker.len()..];
+        let version = rest
+            .iter()
+            .take(64)
+            .position(|&b| b == 0)
+            .map(|end| &rest[..end])
+            .filter(|v| {
+                !v.is_empty()
+                    && v.iter()
+                        .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(b))
+            });
+        if let Some(version) = version {
+            return Ok(Some(String::from_utf8_lossy(version).into_owned()));
+        }
+    }
+    Ok(None)
+}
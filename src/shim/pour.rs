@@ -0,0 +1,405 @@
+use miette::{Context, IntoDiagnostic};
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+#[cfg(not(target_os = "windows"))]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "windows")]
+use crate::constant::ALLOWED_EXTENSIONS;
+use crate::constant::{ENVNAME_MOONUP_SHIM_MODE, SHIMS_FILE};
+use crate::settings::{Settings, ShimMode};
+use crate::toolchain::installed_toolchains;
+
+/// The shims changed by [`rebuild`], named relative to the `MOON_HOME` bin
+/// directory
+#[derive(Debug, Default)]
+pub struct Rebuilt {
+    pub created: Vec<String>,
+    pub refreshed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Rebuilt {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.refreshed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Pour shims for the executables of a toolchain
+///
+/// Shims for the executables in `bin` and `bin/internal` of the toolchain
+/// directory are poured into the `MOON_HOME` bin directory, and recorded as
+/// owned by moonup.
+///
+/// # Returns
+///
+/// The names of the poured shims.
+pub fn pour(toolchain_dir: &Path) -> miette::Result<Vec<String>> {
+    let shim_exe = super::shim_exe();
+    let mode = shim_mode()?;
+    let moon_home_bin = crate::moon_home().join("bin");
+
+    let mut poured = vec![];
+    for name in toolchain_shims(toolchain_dir)? {
+        tracing::debug!("pouring shim for '{}'", name);
+        pour_shim(&shim_exe, &moon_home_bin.join(&name), mode)?;
+        poured.push(name);
+    }
+
+    let mut recorded = recorded_shims();
+    recorded.extend(poured.iter().cloned());
+    record_shims(&recorded)?;
+
+    Ok(poured)
+}
+
+/// Reconcile the shims with the installed toolchains
+///
+/// The expected shims are the union of the executables of all installed
+/// toolchains. Missing shims are created and shims that are not a copy of
+/// the current `moonup-shim` are refreshed. Shims of executables no longer
+/// provided by any toolchain are removed, as long as they were poured by
+/// moonup or carry the shim version marker, other files are left alone.
+///
+/// Without any installed toolchain, the existing recorded shims are kept and
+/// refreshed, so that they can still auto-install the active toolchain.
+pub fn rebuild() -> miette::Result<Rebuilt> {
+    let shim_exe = super::shim_exe();
+    if !shim_exe.is_file() {
+        return Err(miette::miette!(
+            help = "reinstall moonup, `moonup-shim` is shipped alongside `moonup`",
+            "'{}' is not found",
+            shim_exe.display()
+        ));
+    }
+
+    let mode = shim_mode()?;
+    let moon_home_bin = crate::moon_home().join("bin");
+    let toolchains = installed_toolchains()?;
+    let mut expected = BTreeSet::new();
+    for toolchain in toolchains.iter() {
+        expected.extend(toolchain_shims(&toolchain.path)?);
+    }
+    if toolchains.is_empty() {
+        let recorded = recorded_shims();
+        expected = existing_shims(&moon_home_bin)
+            .into_iter()
+            .filter(|(name, path)| recorded.contains(name) || is_shim(path))
+            .map(|(name, _)| name)
+            .collect();
+    }
+
+    let mut rebuilt = Rebuilt::default();
+    for name in expected.iter() {
+        let dest = moon_home_bin.join(name);
+        let changes = match dest.is_file() {
+            false => &mut rebuilt.created,
+            true if is_poured(&dest, &shim_exe, mode) => continue,
+            true => &mut rebuilt.refreshed,
+        };
+
+        tracing::debug!("pouring shim for '{}'", name);
+        pour_shim(&shim_exe, &dest, mode)?;
+        changes.push(name.clone());
+    }
+
+    if toolchains.is_empty() {
+        return Ok(rebuilt);
+    }
+
+    let recorded = recorded_shims();
+    for (name, path) in existing_shims(&moon_home_bin) {
+        if expected.contains(&name) {
+            continue;
+        }
+        if !recorded.contains(&name) && !is_shim(&path) {
+            tracing::debug!("keeping '{}', not poured by moonup", path.display());
+            continue;
+        }
+
+        tracing::debug!("removing stale shim '{}'", name);
+        std::fs::remove_file(&path)
+            .into_diagnostic()
+            .wrap_err(format!("failed to remove stale shim {}", path.display()))?;
+        rebuilt.removed.push(name);
+    }
+
+    record_shims(&expected)?;
+
+    Ok(rebuilt)
+}
+
+/// Get the shim mode, `MOONUP_SHIM_MODE` takes precedence over the
+/// `shim-mode` setting
+pub fn shim_mode() -> miette::Result<ShimMode> {
+    if let Some(value) = std::env::var(ENVNAME_MOONUP_SHIM_MODE)
+        .ok()
+        .filter(|v| !v.is_empty())
+    {
+        return value
+            .parse()
+            .map_err(|e| miette::miette!("{e} in {ENVNAME_MOONUP_SHIM_MODE}"));
+    }
+
+    Ok(Settings::load()?.shim_mode.unwrap_or_default())
+}
+
+/// Pour a shim at `dest` in the given mode, replacing any existing file
+fn pour_shim(shim_exe: &Path, dest: &Path, mode: ShimMode) -> miette::Result<()> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent).into_diagnostic()?;
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let target = link_target(shim_exe);
+        match mode {
+            ShimMode::Copy => {}
+            ShimMode::Symlink => {
+                remove_existing(dest)?;
+                return std::os::unix::fs::symlink(&target, dest)
+                    .into_diagnostic()
+                    .wrap_err(format!(
+                        "failed to link {} to {}",
+                        dest.display(),
+                        target.display()
+                    ));
+            }
+            ShimMode::Hardlink => {
+                remove_existing(dest)?;
+                match std::fs::hard_link(&target, dest) {
+                    Ok(_) => return Ok(()),
+                    Err(e) => tracing::warn!(
+                        "failed to hardlink {} to {}, copying instead: {}",
+                        dest.display(),
+                        target.display(),
+                        e
+                    ),
+                }
+            }
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        if mode != ShimMode::Copy {
+            tracing::debug!("shim mode '{}' is not supported on Windows, copying", mode);
+        }
+    }
+
+    crate::utils::replace_exe(shim_exe, dest)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn remove_existing(path: &Path) -> miette::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e)
+            .into_diagnostic()
+            .wrap_err(format!("failed to remove {}", path.display())),
+        _ => Ok(()),
+    }
+}
+
+/// The `moonup-shim` links point at, resolved so that links survive
+/// `moonup` being invoked through a symlink
+#[cfg(not(target_os = "windows"))]
+fn link_target(shim_exe: &Path) -> PathBuf {
+    shim_exe
+        .canonicalize()
+        .unwrap_or_else(|_| shim_exe.to_owned())
+}
+
+/// Check if the shim at `dest` is poured from the current `moonup-shim` in
+/// the given mode
+fn is_poured(dest: &Path, shim_exe: &Path, mode: ShimMode) -> bool {
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let Ok(metadata) = dest.symlink_metadata() else {
+            return false;
+        };
+        let is_hardlink = metadata.is_file()
+            && shim_exe
+                .metadata()
+                .is_ok_and(|shim| metadata.dev() == shim.dev() && metadata.ino() == shim.ino());
+        let is_copy = || metadata.is_file() && super::is_current(dest, shim_exe).unwrap_or(false);
+        match mode {
+            ShimMode::Copy => !is_hardlink && is_copy(),
+            ShimMode::Symlink => {
+                metadata.is_symlink()
+                    && std::fs::read_link(dest).is_ok_and(|t| t == link_target(shim_exe))
+            }
+            // Copies are poured when hardlinks are not possible
+            ShimMode::Hardlink => is_hardlink || is_copy(),
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let _ = mode;
+        super::is_current(dest, shim_exe).unwrap_or(false)
+    }
+}
+
+/// Check if the file is a `moonup-shim` of any version with a version marker
+fn is_shim(path: &Path) -> bool {
+    super::shim_version(path).is_ok_and(|v| v.is_some())
+}
+
+/// The shim names of the executables of a toolchain
+fn toolchain_shims(toolchain_dir: &Path) -> miette::Result<Vec<String>> {
+    let bin_dir = toolchain_dir.join("bin");
+    let internal_bin_dir = bin_dir.join("internal");
+
+    let mut names = vec![];
+    if bin_dir.is_dir() {
+        let bins = find_bins(&bin_dir).wrap_err("failed to find bins")?;
+        names.extend(bins.iter().map(shim_name));
+    }
+    if internal_bin_dir.is_dir() {
+        let bins = find_bins(&internal_bin_dir).wrap_err("failed to find internal bins")?;
+        names.extend(
+            bins.iter()
+                .map(|bin| format!("internal/{}", shim_name(bin))),
+        );
+    }
+
+    Ok(names)
+}
+
+/// The shim name of an executable, shims are always `.exe` on Windows
+fn shim_name(bin: &OsString) -> String {
+    #[cfg(target_os = "windows")]
+    {
+        Path::new(bin)
+            .with_extension("exe")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        bin.to_string_lossy().into_owned()
+    }
+}
+
+/// The executables in the `MOON_HOME` bin directory and its `internal`
+/// directory
+fn existing_shims(moon_home_bin: &Path) -> Vec<(String, PathBuf)> {
+    let mut shims = vec![];
+    for (prefix, dir) in [
+        ("", moon_home_bin.to_owned()),
+        ("internal/", moon_home_bin.join("internal")),
+    ] {
+        let Ok(read_dir) = dir.read_dir() else {
+            continue;
+        };
+
+        for entry in read_dir.filter_map(std::io::Result::ok) {
+            let path = entry.path();
+            let is_exe = !cfg!(windows) || path.extension().is_some_and(|ext| ext == "exe");
+            // Broken links are listed too, to remove them
+            let is_file = entry
+                .file_type()
+                .is_ok_and(|t| t.is_file() || t.is_symlink());
+            if !is_file || !is_exe || path.is_dir() {
+                continue;
+            }
+            let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            shims.push((name, path));
+        }
+    }
+    shims
+}
+
+fn shims_file() -> PathBuf {
+    crate::moonup_home().join(SHIMS_FILE)
+}
+
+/// The shims recorded as poured by moonup
+fn recorded_shims() -> BTreeSet<String> {
+    std::fs::read_to_string(shims_file())
+        .map(|content| {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn record_shims(shims: &BTreeSet<String>) -> miette::Result<()> {
+    let path = shims_file();
+    let content = shims
+        .iter()
+        .map(|name| format!("{name}\n"))
+        .collect::<String>();
+    std::fs::create_dir_all(crate::moonup_home()).into_diagnostic()?;
+    std::fs::write(&path, content)
+        .into_diagnostic()
+        .wrap_err(format!("failed to write {}", path.display()))
+}
+
+pub(crate) fn find_bins(dir: &Path) -> miette::Result<Vec<OsString>> {
+    let bins = dir
+        .read_dir()
+        .into_diagnostic()
+        .wrap_err(format!("cannot read dir: {}", dir.display()))?
+        .filter_map(std::io::Result::ok)
+        .filter_map(|e| {
+            let path = e.path();
+            let name = e.file_name();
+            let ext = path.extension();
+
+            let is_file = e
+                .file_type()
+                .into_diagnostic()
+                .map(|t| t.is_file())
+                .unwrap_or(false);
+
+            if is_file {
+                #[cfg(target_os = "windows")]
+                {
+                    ext.and_then(|ext| {
+                        let ext = ext.to_str().unwrap_or_default();
+                        ALLOWED_EXTENSIONS.contains(&ext).then_some(name)
+                    })
+                }
+
+                #[cfg(not(target_os = "windows"))]
+                {
+                    // Skip if the file has an extension (.h, .a. .o, etc.)
+                    if ext.is_some() {
+                        return None;
+                    }
+
+                    // Permissions are preserved on extraction, only add the
+                    // missing exec bits for archives that don't record them
+                    if let Ok(metadata) = e.metadata() {
+                        let mode = metadata.permissions().mode();
+                        if mode & 0o111 != 0o111 {
+                            tracing::debug!("marking '{}' as executable", path.display());
+                            let perms = std::fs::Permissions::from_mode(mode | 0o111);
+                            let _ = std::fs::set_permissions(&path, perms).inspect_err(|e| {
+                                tracing::warn!(
+                                    "failed to mark '{}' as executable: {}",
+                                    path.display(),
+                                    e
+                                );
+                            });
+                        }
+                    }
+                    Some(name)
+                }
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(bins)
+}
@@ -51,7 +51,7 @@ pub async fn read_index() -> miette::Result<Index> {
 
     content.clear();
 
-    let mut reader = url_to_reader(main_index_url, &build_http_client_with_retry(), None).await?;
+    let mut reader = url_to_reader(main_index_url, &build_http_client_with_retry()?, None).await?;
     reader
         .read_to_string(&mut content)
         .await
@@ -96,7 +96,7 @@ pub async fn read_channel_index(channel: &ChannelName) -> miette::Result<Channel
     content.clear();
 
     let mut reader =
-        url_to_reader(channel_index_url, &build_http_client_with_retry(), None).await?;
+        url_to_reader(channel_index_url, &build_http_client_with_retry()?, None).await?;
     reader
         .read_to_string(&mut content)
         .await
@@ -119,27 +119,7 @@ pub async fn read_component_index(
     let host_target = Target::from_host()?;
     let filename = format!("{}.json", host_target);
 
-    let mut component_index_local = crate::moonup_home();
-    component_index_local.push("downloads");
-
-    match channel {
-        ChannelName::Bleeding => component_index_local.push("bleeding"),
-        ChannelName::Latest => {
-            component_index_local.push("latest");
-            component_index_local.push(&release.version);
-        }
-        ChannelName::Nightly => {
-            component_index_local.push("nightly");
-            component_index_local.push(
-                release
-                    .date
-                    .as_deref()
-                    .expect("nightly release should have build date"),
-            );
-        }
-        _ => return Err(miette::miette!("unsupported channel: {}", channel)),
-    }
-
+    let mut component_index_local = crate::cache::release_dir(channel, release)?;
     component_index_local.push(&filename);
 
     let (cache_valid, mut content) = match read_json_with_lock(&component_index_local).await {
@@ -176,7 +156,7 @@ pub async fn read_component_index(
     content.clear();
 
     let mut reader =
-        url_to_reader(component_index_url, &build_http_client_with_retry(), None).await?;
+        url_to_reader(component_index_url, &build_http_client_with_retry()?, None).await?;
     reader
         .read_to_string(&mut content)
         .await
@@ -0,0 +1,181 @@
+use miette::{Context, IntoDiagnostic};
+use rattler_digest::{Sha256, compute_file_digest};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::constant::{TOOLCHAIN_INCOMPLETE_FILE, TOOLCHAIN_MANIFEST_FILE, TOOLCHAIN_RECEIPT_FILE};
+
+/// Files in the toolchain directory that are not extracted from the
+/// component archives, relative to the toolchain directory
+const UNTRACKED_FILES: [&str; 4] = [
+    TOOLCHAIN_MANIFEST_FILE,
+    TOOLCHAIN_RECEIPT_FILE,
+    TOOLCHAIN_INCOMPLETE_FILE,
+    "version",
+];
+
+/// Directories generated after extraction, e.g. by bundling the core library
+const UNTRACKED_DIRS: [&str; 2] = ["lib/core/_build", "lib/core/target"];
+
+/// The file manifest of a toolchain
+///
+/// The manifest records every file extracted from the component archives,
+/// and is used to detect modified, missing or extra files later on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The extracted files
+    pub files: Vec<ManifestEntry>,
+}
+
+/// A file recorded in the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The path of the file relative to the toolchain directory, using `/`
+    /// as the separator
+    pub path: String,
+
+    /// The size of the file in bytes
+    pub size: u64,
+
+    /// The SHA256 checksum of the file
+    pub sha256: String,
+}
+
+/// The result of verifying a toolchain against its manifest
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Files whose content differs from the manifest
+    pub modified: Vec<String>,
+
+    /// Files in the manifest that are missing
+    pub missing: Vec<String>,
+
+    /// Files not in the manifest
+    pub extra: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Check if the toolchain matches its manifest
+    pub fn is_intact(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+impl Manifest {
+    /// Build the manifest of the files in the given toolchain directory
+    pub fn scan(toolchain_dir: &Path) -> miette::Result<Self> {
+        let files = tracked_files(toolchain_dir)?
+            .into_iter()
+            .map(|(path, file)| hash_entry(path, &file))
+            .collect::<miette::Result<Vec<_>>>()?;
+
+        Ok(Self { files })
+    }
+
+    /// Read the manifest from the given toolchain directory
+    ///
+    /// # Returns
+    ///
+    /// `None` if the toolchain was installed without a manifest
+    pub fn read(toolchain_dir: &Path) -> miette::Result<Option<Self>> {
+        let path = toolchain_dir.join(TOOLCHAIN_MANIFEST_FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            other => other
+                .into_diagnostic()
+                .wrap_err(format!("failed to read {}", path.display()))?,
+        };
+
+        serde_json::from_str(&content)
+            .into_diagnostic()
+            .wrap_err(format!("malformed manifest {}", path.display()))
+            .map(Some)
+    }
+
+    /// Write the manifest into the given toolchain directory
+    pub fn write(&self, toolchain_dir: &Path) -> miette::Result<()> {
+        let path = toolchain_dir.join(TOOLCHAIN_MANIFEST_FILE);
+        let content = serde_json::to_string_pretty(self).into_diagnostic()?;
+        std::fs::write(&path, content)
+            .into_diagnostic()
+            .wrap_err(format!("failed to write {}", path.display()))
+    }
+
+    /// Re-hash the given toolchain directory and compare it to the manifest
+    pub fn verify(&self, toolchain_dir: &Path) -> miette::Result<VerifyReport> {
+        let mut files = tracked_files(toolchain_dir)?;
+        let mut report = VerifyReport::default();
+
+        for expected in self.files.iter() {
+            let Some(file) = files.remove(&expected.path) else {
+                report.missing.push(expected.path.clone());
+                continue;
+            };
+
+            // Only hash the file if the size matches
+            let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+            if size != expected.size || hash_entry(expected.path.clone(), &file)? != *expected {
+                report.modified.push(expected.path.clone());
+            }
+        }
+
+        report.extra = files.into_keys().collect();
+
+        Ok(report)
+    }
+}
+
+fn hash_entry(path: String, file: &Path) -> miette::Result<ManifestEntry> {
+    let size = file
+        .metadata()
+        .into_diagnostic()
+        .wrap_err(format!("failed to read {}", file.display()))?
+        .len();
+    let sha256 = compute_file_digest::<Sha256>(file)
+        .into_diagnostic()
+        .wrap_err(format!("failed to hash {}", file.display()))?;
+
+    Ok(ManifestEntry {
+        path,
+        size,
+        sha256: format!("{:x}", sha256),
+    })
+}
+
+/// Collect the regular files in the toolchain directory keyed by their
+/// manifest path, skipping untracked files and directories
+fn tracked_files(toolchain_dir: &Path) -> miette::Result<BTreeMap<String, PathBuf>> {
+    fn walk(
+        dir: &Path,
+        prefix: &str,
+        files: &mut BTreeMap<String, PathBuf>,
+    ) -> std::io::Result<()> {
+        for entry in dir.read_dir()?.filter_map(std::io::Result::ok) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let path = match prefix.is_empty() {
+                true => name,
+                false => format!("{prefix}/{name}"),
+            };
+
+            // symlinks are neither followed nor tracked
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if !UNTRACKED_DIRS.contains(&path.as_str()) {
+                    walk(&entry.path(), &path, files)?;
+                }
+            } else if file_type.is_file() && !UNTRACKED_FILES.contains(&path.as_str()) {
+                files.insert(path, entry.path());
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = BTreeMap::new();
+    walk(toolchain_dir, "", &mut files)
+        .into_diagnostic()
+        .wrap_err(format!("failed to read {}", toolchain_dir.display()))?;
+
+    Ok(files)
+}
@@ -4,9 +4,13 @@ use std::path::{Path, PathBuf};
 use crate::dist_server::schema::ChannelName;
 
 pub mod index;
+pub mod manifest;
 pub mod package;
+pub mod receipt;
 pub mod resolve;
 
+use receipt::Receipt;
+
 /// Install specification for a toolchain
 ///
 /// This can be a specific version, or one of the special values:
@@ -163,9 +167,16 @@ pub struct InstalledToolchain {
     /// The install name of the installed toolchain
     pub name: ToolchainSpec,
 
+    /// The install path of the toolchain
+    pub path: PathBuf,
+
     /// The actual version tag (compiler version / build date) of the
     /// installed toolchain
     pub tag: Option<String>,
+
+    /// The install receipt of the toolchain, toolchains installed by older
+    /// versions of moonup don't have one
+    pub receipt: Option<Receipt>,
 }
 
 impl InstalledToolchain {
@@ -176,9 +187,22 @@ impl InstalledToolchain {
             .ok_or_else(|| miette::miette!("failed to read toolchain install name"))?;
 
         let name = ToolchainSpec::from(n);
-        let tag = match &name {
-            ToolchainSpec::Version(_) => None,
-            _ => Some(
+        let receipt = Receipt::read(path)
+            .inspect_err(|e| tracing::warn!("failed to read toolchain receipt: {:?}", e))
+            .ok()
+            .flatten();
+
+        let tag = match (&name, &receipt) {
+            // The tag of a versioned toolchain is only interesting when it
+            // differs from the install name, e.g. for nightly builds
+            (ToolchainSpec::Version(v), Some(receipt)) => {
+                Some(receipt.tag().to_owned()).filter(|tag| tag != v)
+            }
+            (_, Some(receipt)) => Some(receipt.tag().to_owned()),
+            (ToolchainSpec::Version(_), None) => None,
+            // Fall back to the version stub for toolchains installed without
+            // a receipt
+            (_, None) => Some(
                 std::fs::read_to_string(path.join("version"))
                     .map(|s| s.trim().to_owned())
                     .into_diagnostic()
@@ -187,7 +211,51 @@ impl InstalledToolchain {
             ),
         };
 
-        Ok(Self { name, tag })
+        Ok(Self {
+            name,
+            path: path.to_owned(),
+            tag,
+            receipt,
+        })
+    }
+
+    /// Check if the toolchain is broken, i.e. it has no `bin` directory
+    pub fn is_broken(&self) -> bool {
+        !self.path.join("bin").is_dir()
+    }
+
+    /// Check if the install is incomplete, i.e. bundling the core library
+    /// failed after the files were extracted
+    pub fn is_incomplete(&self) -> bool {
+        self.path
+            .join(crate::constant::TOOLCHAIN_INCOMPLETE_FILE)
+            .is_file()
+    }
+
+    /// Check if the `moon` executable is present
+    pub fn has_moon(&self) -> bool {
+        let moon = format!("moon{}", std::env::consts::EXE_SUFFIX);
+        self.path.join("bin").join(moon).is_file()
+    }
+
+    /// Check if the core library has been bundled
+    ///
+    /// Bundles are built into `lib/core/<build dir>/<backend>/release/bundle`,
+    /// the build dir is `_build` for newer toolchains and `target` for older
+    /// ones.
+    pub fn is_core_bundled(&self) -> bool {
+        let core_dir = self.path.join("lib").join("core");
+        ["_build", "target"].iter().any(|build_dir| {
+            core_dir
+                .join(build_dir)
+                .read_dir()
+                .map(|read_dir| {
+                    read_dir
+                        .filter_map(std::io::Result::ok)
+                        .any(|e| e.path().join("release").join("bundle").is_dir())
+                })
+                .unwrap_or(false)
+        })
     }
 }
 
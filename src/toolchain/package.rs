@@ -1,19 +1,23 @@
 use miette::{Context, IntoDiagnostic};
-use std::sync::Arc;
+use std::path::Path;
 
 use crate::{
-    archive::{extract_tar_gz, extract_zip},
+    archive::{ArchiveFormat, extract},
+    dist_server::schema::Component,
     fs::{compute_file_sha256, save_file},
-    reporter::{ProgressReporter, Reporter},
+    reporter::{self, Event, ProgressReader, Stage},
     toolchain::ToolchainSpec,
     utils::{build_dist_server_api, build_http_client_with_retry, path_to_reader, url_to_reader},
 };
 
 use super::index::InstallRecipe;
+use super::manifest::Manifest;
+use super::receipt::Receipt;
 
 pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
-    let mut download_dir = crate::moonup_home();
-    download_dir.push("downloads");
+    // legacy per-channel download directory, looked up to migrate cached
+    // downloads into the content-addressed store
+    let mut legacy_download_dir = crate::cache::downloads_dir();
 
     let mut install_dir_root = crate::moonup_home();
     install_dir_root.push("toolchains");
@@ -21,7 +25,7 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
     // GitHub release tag
     let tag = match recipe.spec {
         ToolchainSpec::Bleeding => {
-            download_dir.push("bleeding");
+            legacy_download_dir.push("bleeding");
 
             install_dir_root.push("bleeding");
 
@@ -30,8 +34,8 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
         ToolchainSpec::Nightly => {
             let date = recipe.release.date.as_deref().expect("should have date");
 
-            download_dir.push("nightly");
-            download_dir.push(date);
+            legacy_download_dir.push("nightly");
+            legacy_download_dir.push(date);
 
             install_dir_root.push("nightly");
 
@@ -40,8 +44,8 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
         ToolchainSpec::Latest => {
             let version = recipe.release.version.as_str();
 
-            download_dir.push("latest");
-            download_dir.push(version);
+            legacy_download_dir.push("latest");
+            legacy_download_dir.push(version);
 
             install_dir_root.push("latest");
 
@@ -51,15 +55,15 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
             if v.starts_with("nightly-") {
                 let date = recipe.release.date.as_deref().expect("should have date");
 
-                download_dir.push("nightly");
-                download_dir.push(date);
+                legacy_download_dir.push("nightly");
+                legacy_download_dir.push(date);
 
                 install_dir_root.push(v);
 
                 v.to_owned()
             } else {
-                download_dir.push("latest");
-                download_dir.push(v);
+                legacy_download_dir.push("latest");
+                legacy_download_dir.push(v);
 
                 install_dir_root.push(v);
 
@@ -73,18 +77,28 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
         .wrap_err(format!("Failed to delete {}", install_dir_root.display()))
         .wrap_err("Unable to clean up existing installation, files may be in use")?;
 
-    let is_bleeding = recipe.spec.is_bleeding();
-
-    // ensure all components are downloaded in the first loop
+    // ensure all components are available in the store in the first loop
     for component in recipe.components.iter() {
         let name = component.name.as_str();
         let file = component.file.as_str();
         let sha256_expected = component.sha256.as_str();
 
-        let local_file = download_dir.join(file);
+        let local_file = crate::cache::blob_path(sha256_expected)?;
+        let reporter = reporter::reporter(name);
+
+        if !local_file.exists() {
+            migrate_legacy_download(
+                &legacy_download_dir.join(file),
+                &local_file,
+                sha256_expected,
+            )
+            .await;
+        }
 
         let mut use_cache = false;
-        if !is_bleeding && local_file.exists() {
+        if local_file.exists() {
+            reporter.on_stage(Stage::Verify);
+            reporter.on_message("cached download");
             match compute_file_sha256(&local_file).await {
                 Ok(sha256) => {
                     let sha256_actual = format!("{:x}", sha256);
@@ -116,23 +130,24 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
             }
         }
 
-        if is_bleeding || !use_cache {
+        if !use_cache {
             tracing::debug!("downloading {} to {}", name, local_file.display());
 
-            let client = build_http_client_with_retry();
+            let client = build_http_client_with_retry()?;
 
             let pathname = format!("/download/{}/{}", tag, file);
             let url = build_dist_server_api(&pathname)?;
 
-            let progress_reporter = ProgressReporter::new(format!("Downloading {}", name));
-            let reporter = Some(Arc::new(progress_reporter) as Arc<dyn Reporter>);
+            reporter.on_stage(Stage::Download);
 
-            let reader = url_to_reader(url, &client, reporter.clone()).await?;
-            let sha256_actual = format!("{:x}", save_file(reader, &local_file).await?);
+            // download to a partial file first, so that an interrupted
+            // download never shows up in the store
+            let partial_file = local_file.with_extension("part");
 
-            if let Some(reporter) = &reporter {
-                reporter.on_complete();
-            }
+            let reader = url_to_reader(url, &client, Some(reporter.clone())).await?;
+            let sha256_actual = format!("{:x}", save_file(reader, &partial_file).await?);
+
+            reporter.on_complete();
 
             if sha256_actual != sha256_expected {
                 let msg = format!(
@@ -141,29 +156,123 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
                 );
 
                 // remove the downloaded invalid file
-                let _ = std::fs::remove_file(&local_file).inspect_err(|e| {
+                let _ = std::fs::remove_file(&partial_file).inspect_err(|e| {
                     tracing::debug!("failed to remove invalid download: {}", e);
                 });
 
                 let err = std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
                 return Err(err).into_diagnostic();
             }
+
+            std::fs::rename(&partial_file, &local_file)
+                .into_diagnostic()
+                .wrap_err(format!("failed to store {}", local_file.display()))?;
         }
+
+        reporter.on_complete();
+        reporter::emit(Event::ChecksumVerified {
+            component: name.to_owned(),
+            sha256: sha256_expected.to_owned(),
+        });
     }
 
     // do the actual installation in the second loop
+    if let Err(err) = extract_components(recipe, &install_dir_root).await {
+        // clean up the invalid installation
+        let _ = crate::fs::remove_dir_all(&install_dir_root).inspect_err(|e| {
+            tracing::debug!("failed to clean up invalid installation: {}", e);
+        });
+        return Err(err);
+    }
+
+    // record the extracted files before anything else is written into the
+    // toolchain directory
+    let toolchain_dir = install_dir_root.clone();
+    let manifest = tokio::task::spawn_blocking(move || Manifest::scan(&toolchain_dir))
+        .await
+        .into_diagnostic()??;
+    manifest.write(&install_dir_root)?;
+
+    Receipt::from_recipe(recipe)?.write(&install_dir_root)?;
+
+    // create a stub to store the actual version when the spec is latest or nightly,
+    // the stub is kept for older versions of moonup that don't read receipts
+    if recipe.spec.is_latest() || recipe.spec.is_bleeding() {
+        let actual_version = recipe.release.version.as_str();
+        install_dir_root.push("version");
+        tokio::fs::write(&install_dir_root, format!("{}\n", actual_version))
+            .await
+            .into_diagnostic()?;
+    } else if recipe.spec.is_nightly() {
+        let actual_date = recipe.release.date.as_ref().expect("should have a date");
+        install_dir_root.push("version");
+        tokio::fs::write(&install_dir_root, format!("{}\n", actual_date))
+            .await
+            .into_diagnostic()?;
+    }
+
+    Ok(())
+}
+
+/// Extract the components of the recipe from the store into the toolchain
+/// directory, overwriting existing files
+///
+/// The components are expected to be available in the store already. They
+/// are extracted into a staging directory first, the toolchain directory is
+/// left untouched if any of them fails to extract.
+pub async fn extract_components(
+    recipe: &InstallRecipe,
+    install_dir_root: &Path,
+) -> miette::Result<()> {
+    let dirname = install_dir_root
+        .file_name()
+        .ok_or_else(|| miette::miette!("invalid install path {}", install_dir_root.display()))?;
+    let staging_dir = crate::moonup_home().join("tmp").join(dirname);
+    crate::fs::remove_dir_all(&staging_dir)
+        .into_diagnostic()
+        .wrap_err(format!("failed to clean up {}", staging_dir.display()))?;
+
+    let result = extract_components_into(recipe, &staging_dir)
+        .await
+        .and_then(|_| {
+            crate::fs::merge_dir(&staging_dir, install_dir_root)
+                .into_diagnostic()
+                .wrap_err(format!(
+                    "failed to install into {}",
+                    install_dir_root.display()
+                ))
+        });
+
+    let _ = crate::fs::remove_dir_all(&staging_dir).inspect_err(|e| {
+        tracing::debug!("failed to clean up staging directory: {}", e);
+    });
+
+    result
+}
+
+async fn extract_components_into(
+    recipe: &InstallRecipe,
+    install_dir_root: &Path,
+) -> miette::Result<()> {
     for component in recipe.components.iter() {
-        let mut component_install_dir = install_dir_root.clone();
+        let mut component_install_dir = install_dir_root.to_owned();
         let name = component.name.as_str();
         let file = component.file.as_str();
         let sha256_expected = component.sha256.as_str();
 
-        let local_file = download_dir.join(file);
+        let local_file = crate::cache::blob_path(sha256_expected)?;
         tracing::debug!("installing {} from {}", name, local_file.display());
 
+        let reporter = reporter::reporter(name);
+        reporter.on_stage(Stage::Extract);
+        if let Ok(metadata) = local_file.metadata() {
+            reporter.on_start(metadata.len() as usize);
+        }
+
         let reader = path_to_reader(&local_file)
             .await
             .wrap_err("failed to read local file")?;
+        let reader = ProgressReader::new(reader, reporter.clone());
 
         // older toolchains (<= v0.1.20241223+62b9a1a85) don't have a `bin` subdirectory,
         // install all toolchain files into the `bin` subdirectory
@@ -178,11 +287,11 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
             component_install_dir.push("lib");
         }
 
-        let is_zip = file.ends_with(".zip");
-        let sha256 = match is_zip {
-            true => extract_zip(reader, &component_install_dir).await?,
-            false => extract_tar_gz(reader, &component_install_dir).await?,
-        };
+        let format = archive_format(component, &local_file)?;
+        tracing::debug!("extracting {} as {}", name, format);
+        let sha256 = extract(reader, format, &component_install_dir).await;
+        reporter.on_complete();
+        let sha256 = sha256?;
 
         let sha256_actual = format!("{:x}", sha256);
 
@@ -196,30 +305,53 @@ pub async fn populate_install(recipe: &InstallRecipe) -> miette::Result<()> {
             let _ = std::fs::remove_file(&local_file).inspect_err(|e| {
                 tracing::debug!("failed to remove invalid component download: {}", e);
             });
-            // clean up the invalid installation
-            let _ = crate::fs::remove_dir_all(&install_dir_root).inspect_err(|e| {
-                tracing::debug!("failed to clean up invalid installation: {}", e);
-            });
 
             let err = std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
             return Err(err).into_diagnostic();
         }
     }
 
-    // create a stub to store the actual version when the spec is latest or nightly
-    if recipe.spec.is_latest() || recipe.spec.is_bleeding() {
-        let actual_version = recipe.release.version.as_str();
-        install_dir_root.push("version");
-        tokio::fs::write(&install_dir_root, format!("{}\n", actual_version))
-            .await
-            .into_diagnostic()?;
-    } else if recipe.spec.is_nightly() {
-        let actual_date = recipe.release.date.as_ref().expect("should have a date");
-        install_dir_root.push("version");
-        tokio::fs::write(&install_dir_root, format!("{}\n", actual_date))
-            .await
-            .into_diagnostic()?;
+    Ok(())
+}
+
+/// Get the archive format of a component from its declared format, or by the
+/// magic bytes of the downloaded file
+fn archive_format(component: &Component, local_file: &Path) -> miette::Result<ArchiveFormat> {
+    if let Some(declared) = component.format.as_deref() {
+        match declared.parse::<ArchiveFormat>() {
+            Ok(format) => return Ok(format),
+            Err(e) => tracing::debug!("{}, detecting the format of {}", e, component.file),
+        }
     }
 
-    Ok(())
+    ArchiveFormat::detect(local_file)
+        .into_diagnostic()
+        .wrap_err(format!("failed to read {}", local_file.display()))?
+        .ok_or_else(|| miette::miette!("unsupported archive format of {}", component.file))
+}
+
+/// Move a download cached in the legacy per-channel layout into the store
+///
+/// The legacy file is only moved if its checksum matches, otherwise it is
+/// left untouched.
+async fn migrate_legacy_download(legacy_file: &Path, blob: &Path, sha256_expected: &str) {
+    if !legacy_file.is_file() {
+        return;
+    }
+
+    match compute_file_sha256(legacy_file).await {
+        Ok(sha256) if format!("{:x}", sha256) == sha256_expected => {
+            tracing::debug!(
+                "migrating cached download {} to {}",
+                legacy_file.display(),
+                blob.display()
+            );
+            let result = std::fs::create_dir_all(blob.parent().expect("invalid blob path"))
+                .and_then(|_| std::fs::rename(legacy_file, blob));
+            if let Err(e) = result {
+                tracing::debug!("failed to migrate cached download: {}", e);
+            }
+        }
+        _ => tracing::debug!("ignoring legacy cached download {}", legacy_file.display()),
+    }
 }
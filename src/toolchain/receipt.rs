@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use miette::{Context, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::constant::TOOLCHAIN_RECEIPT_FILE;
+use crate::dist_server::schema::{Component, Release, Target};
+
+use super::ToolchainSpec;
+use super::index::InstallRecipe;
+
+/// The install receipt of a toolchain
+///
+/// The receipt is written into the toolchain directory at install time and
+/// records what was actually installed for the toolchain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    /// The requested toolchain spec
+    pub spec: String,
+
+    /// The resolved release information
+    pub release: Release,
+
+    /// The distribution server the toolchain was installed from
+    pub dist_server: String,
+
+    /// The target host of the installed toolchain
+    pub target: Target,
+
+    /// The installed components
+    pub components: Vec<Component>,
+
+    /// The version of moonup that installed the toolchain
+    pub moonup_version: String,
+
+    /// The install timestamp
+    pub installed_at: DateTime<Utc>,
+}
+
+impl Receipt {
+    /// Create a receipt for the given install recipe
+    pub fn from_recipe(recipe: &InstallRecipe) -> miette::Result<Self> {
+        Ok(Self {
+            spec: recipe.spec.to_string(),
+            release: recipe.release.clone(),
+            dist_server: crate::auth::redact(&crate::utils::dist_server_baseurl()),
+            target: Target::from_host()?,
+            components: recipe.components.clone(),
+            moonup_version: env!("CARGO_PKG_VERSION").to_owned(),
+            installed_at: Utc::now(),
+        })
+    }
+
+    /// Rebuild the install recipe the toolchain was installed from
+    pub fn to_recipe(&self) -> InstallRecipe {
+        InstallRecipe {
+            spec: ToolchainSpec::from(self.spec.as_str()),
+            release: self.release.clone(),
+            components: self.components.clone(),
+        }
+    }
+
+    /// Read the receipt from the given toolchain directory
+    ///
+    /// # Returns
+    ///
+    /// `None` if the toolchain was installed without a receipt
+    pub fn read(toolchain_dir: &Path) -> miette::Result<Option<Self>> {
+        let path = toolchain_dir.join(TOOLCHAIN_RECEIPT_FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            other => other
+                .into_diagnostic()
+                .wrap_err(format!("failed to read {}", path.display()))?,
+        };
+
+        serde_json::from_str(&content)
+            .into_diagnostic()
+            .wrap_err(format!("malformed receipt {}", path.display()))
+            .map(Some)
+    }
+
+    /// Write the receipt into the given toolchain directory
+    pub fn write(&self, toolchain_dir: &Path) -> miette::Result<()> {
+        let path = toolchain_dir.join(TOOLCHAIN_RECEIPT_FILE);
+        let content = serde_json::to_string_pretty(self).into_diagnostic()?;
+        std::fs::write(&path, content)
+            .into_diagnostic()
+            .wrap_err(format!("failed to write {}", path.display()))
+    }
+
+    /// Get the actual version tag of the installed toolchain
+    ///
+    /// This is the build date for the `nightly` toolchain, and the compiler
+    /// version for all other toolchains.
+    pub fn tag(&self) -> &str {
+        match ToolchainSpec::from(self.spec.as_str()) {
+            ToolchainSpec::Nightly => self.release.date.as_deref().unwrap_or("unknown"),
+            _ => self.release.version.as_str(),
+        }
+    }
+}
@@ -1,7 +1,103 @@
 use std::ffi::OsStr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::{constant::TOOLCHAIN_FILE, utils::trimmed_or_none};
+use crate::{
+    constant::{ENVNAME_MOONUP_TOOLCHAIN_SPEC, TOOLCHAIN_FILE},
+    utils::trimmed_or_none,
+};
+
+/// The content of a [`TOOLCHAIN_FILE`]
+///
+/// The toolchain spec is on a line of its own, other lines may set options
+/// as `key = value`, e.g. `auto-install = never`. Blank lines and lines
+/// starting with `#` are ignored.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ToolchainFile {
+    /// The pinned toolchain spec
+    pub toolchain: Option<String>,
+
+    /// The options in the order of appearance
+    pub options: Vec<(String, String)>,
+}
+
+impl ToolchainFile {
+    pub fn parse(content: &str) -> Self {
+        let mut file = Self::default();
+        for line in content.lines().filter_map(trimmed_or_none) {
+            if line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key, value)) => file
+                    .options
+                    .push((key.trim().to_owned(), value.trim().to_owned())),
+                None if file.toolchain.is_none() => file.toolchain = Some(line.to_owned()),
+                None => {}
+            }
+        }
+        file
+    }
+
+    /// Read and parse the toolchain file at the given path
+    pub fn read(path: &Path) -> Option<Self> {
+        std::fs::read_to_string(path)
+            .ok()
+            .map(|content| Self::parse(&content))
+    }
+
+    /// Get the value of the given option
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Check if the line is a toolchain spec line
+    pub fn is_toolchain_line(line: &str) -> bool {
+        trimmed_or_none(line).is_some_and(|line| !line.starts_with('#') && !line.contains('='))
+    }
+}
+
+/// Where the active toolchain is requested from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolchainSource {
+    /// The `+<toolchain>` argument of a shim
+    Argument,
+
+    /// The `MOONUP_TOOLCHAIN_SPEC` environment variable
+    Environment,
+
+    /// A [`TOOLCHAIN_FILE`] of the current directory or one of its parents
+    ToolchainFile(PathBuf),
+
+    /// The `$MOONUP_HOME/default` file
+    Default(PathBuf),
+
+    /// Neither pinned nor set as the default
+    Fallback,
+}
+
+impl std::fmt::Display for ToolchainSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolchainSource::Argument => write!(f, "the `+<toolchain>` argument"),
+            ToolchainSource::Environment => {
+                write!(
+                    f,
+                    "the {ENVNAME_MOONUP_TOOLCHAIN_SPEC} environment variable"
+                )
+            }
+            ToolchainSource::ToolchainFile(path) => write!(f, "{}", path.display()),
+            ToolchainSource::Default(path) => {
+                write!(f, "the default toolchain in {}", path.display())
+            }
+            ToolchainSource::Fallback => {
+                write!(f, "the fallback, no toolchain is pinned or set as default")
+            }
+        }
+    }
+}
 
 /// Iterates over the current directory and all its parent directories to find
 /// if there is a [`TOOLCHAIN_FILE`].
@@ -22,12 +118,8 @@ pub fn resolve_toolchain_file() -> Option<PathBuf> {
 /// The pinned toolchain version if found
 pub fn detect_pinned_toolchain() -> Option<String> {
     resolve_toolchain_file()
-        .and_then(|path| {
-            std::fs::read_to_string(path)
-                .map(|s| trimmed_or_none(&s).map(str::to_owned))
-                .ok()
-        })
-        .flatten()
+        .and_then(|path| ToolchainFile::read(&path))
+        .and_then(|file| file.toolchain)
 }
 
 /// Detect the default toolchain
@@ -38,8 +130,7 @@ pub fn detect_pinned_toolchain() -> Option<String> {
 ///
 /// The default toolchain version if found
 pub fn detect_default_toolchain() -> Option<String> {
-    let default_file = crate::moonup_home().join("default");
-    std::fs::read_to_string(default_file)
+    std::fs::read_to_string(default_toolchain_file())
         .map(|s| trimmed_or_none(&s).map(str::to_owned))
         .ok()
         .flatten()
@@ -68,9 +159,29 @@ pub fn detect_active_toolchain() -> PathBuf {
 /// This function is used by the `moonup-shim`, and because we don't want to
 /// bloated the shim, miette/tracing should not be used here.
 pub fn detect_active_toolchainspec() -> String {
-    detect_pinned_toolchain()
-        .or(detect_default_toolchain())
-        .unwrap_or("latest".to_string())
+    detect_active_toolchainspec_with_source().0
+}
+
+/// Same as [`detect_active_toolchainspec`], also returning where the
+/// toolchain spec is found
+pub fn detect_active_toolchainspec_with_source() -> (String, ToolchainSource) {
+    if let Some(path) = resolve_toolchain_file()
+        && let Some(toolchain) = ToolchainFile::read(&path).and_then(|file| file.toolchain)
+    {
+        return (toolchain, ToolchainSource::ToolchainFile(path));
+    }
+
+    match detect_default_toolchain() {
+        Some(toolchain) => (
+            toolchain,
+            ToolchainSource::Default(default_toolchain_file()),
+        ),
+        None => ("latest".to_string(), ToolchainSource::Fallback),
+    }
+}
+
+fn default_toolchain_file() -> PathBuf {
+    crate::moonup_home().join("default")
 }
 
 /// Resolves the executable binary in the given paths.
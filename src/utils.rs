@@ -1,24 +1,36 @@
 use futures_util::TryStreamExt;
-#[cfg(target_os = "windows")]
-use miette::Context;
-use miette::IntoDiagnostic;
-use reqwest::Client;
+use miette::{Context, IntoDiagnostic};
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
-use reqwest_retry::{RetryTransientMiddleware, policies::ExponentialBackoff};
 use std::env;
 #[cfg(not(target_os = "windows"))]
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncRead, BufReader};
 use tokio_util::io::StreamReader;
 use url::Url;
 
+use crate::auth::redact_url;
 use crate::reporter::Reporter;
+use crate::retry::RetryMiddleware;
+use crate::settings::{Network, Settings};
+
+/// Load the `[network]` settings with the environment variable overrides
+pub(crate) fn network_settings() -> miette::Result<Network> {
+    Settings::load()?.network.with_env()
+}
 
 /// Build a basic HTTP client
-pub(crate) fn build_http_client() -> Client {
+///
+/// Proxies, extra CA certificates, the TLS client certificate and timeouts
+/// are configured by the `[network]` settings and their environment variables.
+pub(crate) fn build_http_client() -> miette::Result<Client> {
+    http_client(&network_settings()?)
+}
+
+fn http_client(network: &Network) -> miette::Result<Client> {
     static APP_USER_AGENT: &str = concat!(
         env!("CARGO_PKG_NAME"),
         "/",
@@ -28,32 +40,119 @@ pub(crate) fn build_http_client() -> Client {
         ")"
     );
 
-    Client::builder()
+    let builder = Client::builder()
         .user_agent(APP_USER_AGENT)
-        .read_timeout(Duration::from_secs(crate::constant::HTTP_READ_TIMEOUT))
+        .connect_timeout(network.connect_timeout)
+        .read_timeout(network.read_timeout);
+
+    configure_network(builder, network)?
         .build()
-        .expect("failed to build HTTP client")
+        .into_diagnostic()
+        .wrap_err("failed to build HTTP client")
+}
+
+fn configure_network(
+    mut builder: reqwest::ClientBuilder,
+    network: &Network,
+) -> miette::Result<reqwest::ClientBuilder> {
+    if let Some(proxy) = network.proxy.as_deref() {
+        tracing::debug!("using proxy {}", proxy);
+        let no_proxy = match network.no_proxy.as_deref() {
+            Some(no_proxy) => NoProxy::from_string(no_proxy),
+            None => NoProxy::from_env(),
+        };
+        let proxy = Proxy::all(proxy)
+            .into_diagnostic()
+            .wrap_err(format!("invalid proxy '{}'", proxy))?
+            .no_proxy(no_proxy);
+        builder = builder.proxy(proxy);
+    } else if let Some(no_proxy) = network.no_proxy.as_deref() {
+        // Apply the no-proxy list to the proxies from the environment
+        for var in ["https_proxy", "http_proxy", "all_proxy"] {
+            let url = env::var(var).or_else(|_| env::var(var.to_ascii_uppercase()));
+            if let Ok(url) = url {
+                let proxy = match var {
+                    "https_proxy" => Proxy::https(&url),
+                    "http_proxy" => Proxy::http(&url),
+                    _ => Proxy::all(&url),
+                };
+                let proxy = proxy
+                    .into_diagnostic()
+                    .wrap_err(format!("invalid proxy '{}'", url))?;
+                builder = builder.proxy(proxy.no_proxy(NoProxy::from_string(no_proxy)));
+            }
+        }
+    }
+
+    let mut certs = vec![];
+    if let Some(path) = network.ca_bundle.as_deref() {
+        let pem = std::fs::read(path)
+            .into_diagnostic()
+            .wrap_err(format!("failed to read CA bundle {}", path.display()))?;
+        certs = Certificate::from_pem_bundle(&pem)
+            .into_diagnostic()
+            .wrap_err(format!("malformed CA bundle {}", path.display()))?;
+        tracing::debug!(
+            "trusting {} certificate(s) from {}",
+            certs.len(),
+            path.display()
+        );
+    }
+    builder = match network.system_roots {
+        true => builder.tls_certs_merge(certs),
+        false => builder.tls_certs_only(certs),
+    };
+
+    if let Some(path) = network.client_cert.as_deref() {
+        let mut pem = std::fs::read(path).into_diagnostic().wrap_err(format!(
+            "failed to read client certificate {}",
+            path.display()
+        ))?;
+        if let Some(key) = network.client_key.as_deref() {
+            pem.push(b'\n');
+            pem.extend(
+                std::fs::read(key)
+                    .into_diagnostic()
+                    .wrap_err(format!("failed to read client key {}", key.display()))?,
+            );
+        }
+        let identity = Identity::from_pem(&pem)
+            .into_diagnostic()
+            .wrap_err(format!("malformed client certificate {}", path.display()))?;
+        builder = builder.identity(identity);
+    }
+
+    Ok(builder)
 }
 
 /// Build an HTTP client with exponential backoff retry policy
-pub(crate) fn build_http_client_with_retry() -> ClientWithMiddleware {
-    let retry_policy = ExponentialBackoff::builder().build_with_max_retries(3);
-    let retry_middleware = RetryTransientMiddleware::new_with_policy(retry_policy);
+pub(crate) fn build_http_client_with_retry() -> miette::Result<ClientWithMiddleware> {
+    let network = network_settings()?;
+    let retry_middleware = RetryMiddleware::new(
+        network.retries,
+        network.retry_min_delay,
+        network.retry_max_delay,
+    );
 
-    ClientBuilder::new(build_http_client())
+    Ok(ClientBuilder::new(http_client(&network)?)
         .with(retry_middleware)
-        .build()
+        .build())
+}
+
+/// Get the base URL of the distribution server
+pub(crate) fn dist_server_baseurl() -> String {
+    env::var(crate::constant::ENVNAME_MOONUP_DIST_SERVER)
+        .unwrap_or_else(|_| crate::constant::MOONUP_DIST_SERVER.to_string())
 }
 
 pub(crate) fn build_dist_server_api(path: &str) -> miette::Result<Url> {
     let path = path.trim_start_matches('/');
 
-    let baseurl = env::var(crate::constant::ENVNAME_MOONUP_DIST_SERVER)
-        .unwrap_or_else(|_| crate::constant::MOONUP_DIST_SERVER.to_string());
+    let baseurl = dist_server_baseurl();
     Url::parse(&format!("{}/{}", baseurl, path))
         .into_diagnostic()
         .inspect(|u| {
-            tracing::trace!("constructed dist server API: {}", u);
+            tracing::trace!("constructed dist server API: {}", redact_url(u));
         })
 }
 
@@ -62,14 +161,14 @@ pub async fn url_to_reader(
     client: &ClientWithMiddleware,
     reporter: Option<Arc<dyn Reporter>>,
 ) -> miette::Result<impl AsyncRead + use<>> {
-    tracing::debug!("streaming: {}", url);
-    let request = client.get(url);
+    tracing::debug!("streaming: {}", redact_url(&url));
+    let request = crate::auth::get(client, url)?;
     let response = request.send().await.into_diagnostic()?;
 
     if !response.status().is_success() {
         return Err(std::io::Error::other(format!(
             "failed to download {} (code: {})",
-            response.url(),
+            redact_url(response.url()),
             response.status()
         )))
         .into_diagnostic();
@@ -84,17 +183,33 @@ pub async fn url_to_reader(
         );
     }
 
+    let rate_limit = network_settings()?
+        .download_rate_limit
+        .filter(|&limit| limit > 0);
+    let started_at = Instant::now();
     let mut current = 0;
 
     let byte_stream = response
         .bytes_stream()
-        .inspect_ok(move |chunk| {
+        .map_err(std::io::Error::other)
+        .and_then(move |chunk| {
             current += chunk.len();
             if let Some(reporter) = &reporter {
                 reporter.on_progress(current);
             }
-        })
-        .map_err(std::io::Error::other);
+
+            // Wait until the average speed drops to the rate limit
+            let delay = rate_limit.and_then(|limit| {
+                let expected = Duration::from_secs_f64(current as f64 / limit as f64);
+                expected.checked_sub(started_at.elapsed())
+            });
+            Box::pin(async move {
+                if let Some(delay) = delay {
+                    tokio::time::sleep(delay).await;
+                }
+                Ok(chunk)
+            })
+        });
 
     Ok(StreamReader::new(byte_stream))
 }
@@ -104,6 +219,71 @@ pub async fn path_to_reader(path: &Path) -> miette::Result<impl AsyncRead + use<
     Ok(BufReader::new(file))
 }
 
+/// Parse a size, e.g. `500M`, `2G`, `1GiB` or `1024`
+pub(crate) fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+
+    let value = value
+        .parse::<f64>()
+        .map_err(|_| format!("invalid size '{s}'"))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1u64,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "invalid size unit in '{s}', expected one of K, M, G, T"
+            ));
+        }
+    };
+
+    Ok((value * multiplier as f64) as u64)
+}
+
+/// Parse a duration, e.g. `500ms`, `30s`, `5m`, `1h`, `2d` or `30` (seconds)
+pub(crate) fn parse_duration(s: &str) -> Result<Duration, String> {
+    parse_duration_in(s, "s")
+}
+
+/// Parse a duration, a value without a unit is in the given `default_unit`
+pub(crate) fn parse_duration_in(s: &str, default_unit: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration '{s}'"))?;
+    let unit = match unit.trim() {
+        "" => default_unit,
+        unit => unit,
+    };
+    let millis = match unit {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        "d" => 24 * 60 * 60 * 1000,
+        "w" => 7 * 24 * 60 * 60 * 1000,
+        _ => {
+            return Err(format!(
+                "invalid duration unit in '{s}', expected one of ms, s, m, h, d, w"
+            ));
+        }
+    };
+
+    value
+        .checked_mul(millis)
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("duration '{s}' is too large"))
+}
+
 /// Trim the given string and return `None` if the string is empty.
 #[inline]
 pub(crate) fn trimmed_or_none(s: &str) -> Option<&str> {
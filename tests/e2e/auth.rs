@@ -0,0 +1,128 @@
+use mockito::{Matcher, Server};
+use moonup::constant;
+use std::fs;
+
+use crate::util::TestWorkspace;
+
+/// `user:pass` encoded as basic auth
+const BASIC_AUTH: &str = "Basic dXNlcjpwYXNz";
+
+fn index_mock(server: &mut Server, authorization: &str) -> mockito::Mock {
+    server
+        .mock("GET", "/index.json")
+        .match_header("authorization", authorization)
+        .with_body_from_file("tests/fixtures/dist_server/index.json")
+        .with_header("content-type", "application/json")
+        .create()
+}
+
+#[test]
+fn test_dist_server_token() {
+    let ws = TestWorkspace::new();
+    let mut server = Server::new();
+    let mock = index_mock(&mut server, "Bearer secret-token");
+
+    let output = ws
+        .cli()
+        .env(constant::ENVNAME_MOONUP_DIST_SERVER, server.url())
+        .env(constant::ENVNAME_MOONUP_DIST_SERVER_TOKEN, "secret-token")
+        .args(["install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    mock.assert();
+}
+
+#[test]
+fn test_auth_settings() {
+    let ws = TestWorkspace::new();
+    let mut server = Server::new();
+    let mock = index_mock(&mut server, BASIC_AUTH);
+
+    fs::create_dir_all(ws.moonup_home()).expect("should create moonup home");
+    fs::write(
+        ws.moonup_home().join("settings.toml"),
+        format!(
+            "[auth.\"{}\"]\nusername = \"user\"\npassword = \"pass\"\n",
+            server.host_with_port()
+        ),
+    )
+    .expect("should create settings");
+
+    let output = ws
+        .cli()
+        .env(constant::ENVNAME_MOONUP_DIST_SERVER, server.url())
+        .args(["install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    mock.assert();
+}
+
+#[test]
+fn test_netrc() {
+    let ws = TestWorkspace::new();
+    let mut server = Server::new();
+    let mock = index_mock(&mut server, BASIC_AUTH);
+
+    let netrc = ws.tempdir().path().join("netrc");
+    fs::write(
+        &netrc,
+        "machine example.com login other password other\n\
+         machine 127.0.0.1\n  login user\n  password pass\n",
+    )
+    .expect("should create netrc");
+
+    let output = ws
+        .cli()
+        .env(constant::ENVNAME_MOONUP_DIST_SERVER, server.url())
+        .env(constant::ENVNAME_NETRC, &netrc)
+        .args(["install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    mock.assert();
+}
+
+#[test]
+fn test_credentials_redacted() {
+    let ws = TestWorkspace::new();
+    let mut server = Server::new();
+    let mock = index_mock(&mut server, BASIC_AUTH);
+    let unauthorized = server.mock("GET", Matcher::Any).with_status(401).create();
+
+    let dist_server = server.url().replace("http://", "http://user:pass@");
+    let output = ws
+        .cli()
+        .env(constant::ENVNAME_MOONUP_DIST_SERVER, &dist_server)
+        .args(["-vvvv", "install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    mock.assert();
+    unauthorized.expect(0).assert();
+
+    let logs = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(logs.contains("constructed dist server API: http://***:***@"));
+    assert!(!logs.contains("user:pass"));
+}
@@ -0,0 +1,193 @@
+use moonup::constant;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+
+use crate::util::TestWorkspace;
+
+fn run_moon(ws: &TestWorkspace, env: &[(&str, &str)]) -> Output {
+    let mut cmd = ws.cmd(ws.shim("moon"));
+    cmd.envs(env.iter().copied())
+        .arg("version")
+        .stdin(Stdio::null())
+        .output()
+        .expect("should run moon shim")
+}
+
+#[test]
+fn test_auto_install_never_from_env() {
+    let ws = TestWorkspace::new();
+    let toolchain_file = ws.project_path().join(constant::TOOLCHAIN_FILE);
+    fs::write(&toolchain_file, "0.1.20241231+ba15a9a4e\n").expect("should pin toolchain");
+
+    let output = run_moon(&ws, &[(constant::ENVNAME_MOONUP_AUTO_INSTALL, "never")]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!(
+        "toolchain '0.1.20241231+ba15a9a4e' is not installed (requested by {})",
+        toolchain_file.display()
+    )));
+    assert!(stderr.contains("auto-install is 'never' as set by MOONUP_AUTO_INSTALL"));
+    assert!(stderr.contains("run `moonup install 0.1.20241231+ba15a9a4e` to install it"));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_auto_install_never_from_toolchain_file() {
+    let ws = TestWorkspace::new();
+    let toolchain_file = ws.project_path().join(constant::TOOLCHAIN_FILE);
+    fs::write(&toolchain_file, "nightly\nauto-install = never\n").expect("should pin toolchain");
+
+    let output = run_moon(&ws, &[]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("toolchain 'nightly' is not installed"));
+    assert!(stderr.contains(&format!(
+        "auto-install is 'never' as set by {}",
+        toolchain_file.display()
+    )));
+}
+
+#[test]
+fn test_auto_install_prompt_without_terminal() {
+    let ws = TestWorkspace::new();
+    fs::create_dir_all(ws.moonup_home()).expect("should create moonup home");
+    fs::write(
+        ws.moonup_home().join("settings.toml"),
+        "auto-install = \"prompt\"\n",
+    )
+    .expect("should create settings");
+
+    let output = run_moon(&ws, &[]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("toolchain 'latest' is not installed (requested by the fallback"));
+    assert!(stderr.contains("but there is no terminal to prompt on"));
+}
+
+#[test]
+fn test_invalid_auto_install_policy() {
+    let ws = TestWorkspace::new();
+
+    let output = run_moon(&ws, &[(constant::ENVNAME_MOONUP_AUTO_INSTALL, "sometimes")]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("invalid auto-install policy 'sometimes'")
+    );
+}
+
+/// Create a fake `moonup` in the given directory, failing to install
+#[cfg(unix)]
+fn fake_moonup(dir: &Path, name: &str) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::create_dir_all(dir).expect("should create bin dir");
+    let moonup = dir.join("moonup");
+    fs::write(
+        &moonup,
+        format!("#!/bin/sh\necho \"stdout of {name} $*\"\necho \"stderr of {name}\" >&2\nexit 1\n"),
+    )
+    .expect("should create fake moonup");
+    fs::set_permissions(&moonup, fs::Permissions::from_mode(0o755))
+        .expect("should make fake moonup executable");
+    moonup
+}
+
+/// Put a fake `moonup` on PATH
+#[cfg(unix)]
+fn fake_moonup_on_path(ws: &TestWorkspace) -> String {
+    let bin_dir = ws.tempdir().path().join("fake-bin");
+    fake_moonup(&bin_dir, "moonup");
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let paths = std::iter::once(bin_dir).chain(std::env::split_paths(&path));
+    std::env::join_paths(paths)
+        .expect("should join PATH")
+        .into_string()
+        .expect("PATH should be valid UTF-8")
+}
+
+#[test]
+#[cfg(unix)]
+fn test_auto_install_output_off_stdout() {
+    let ws = TestWorkspace::new();
+    let path = fake_moonup_on_path(&ws);
+
+    let output = run_moon(&ws, &[("PATH", &path)]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // stdout is not a terminal, so the install output goes to the log file
+    let log = ws.moonup_home().join("logs").join(constant::SHIM_LOG_FILE);
+    let content = fs::read_to_string(&log).expect("should write shim log");
+    assert!(content.contains("toolchain version 'latest' not installed"));
+    assert!(content.contains("stdout of moonup install latest"));
+    assert!(content.contains("stderr of moonup"));
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("installing (see {})", log.display())));
+    assert!(!stderr.contains("of moonup"));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_auto_install_quiet_for_lsp() {
+    let ws = TestWorkspace::new();
+    let path = fake_moonup_on_path(&ws);
+
+    let output = ws
+        .cmd(ws.shim("moon-lsp"))
+        .env("PATH", &path)
+        .stdin(Stdio::null())
+        .output()
+        .expect("should run moon-lsp shim");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // Only the error is reported, pointing at the log file
+    let log = ws.moonup_home().join("logs").join(constant::SHIM_LOG_FILE);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.trim(),
+        format!(
+            "Error: Failed to install active toolchain 'latest', see {}",
+            log.display()
+        )
+    );
+    assert!(
+        fs::read_to_string(&log)
+            .expect("should write shim log")
+            .contains("stdout of moonup install latest")
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_auto_install_prefers_owning_moonup() {
+    let ws = TestWorkspace::new();
+    let path = fake_moonup_on_path(&ws);
+    let log = ws.moonup_home().join("logs").join(constant::SHIM_LOG_FILE);
+
+    // `$MOONUP_HOME/bin/moonup` is preferred over PATH
+    fake_moonup(&ws.moonup_home().join("bin"), "home moonup");
+    let output = run_moon(&ws, &[("PATH", &path)]);
+    assert!(!output.status.success());
+    let content = fs::read_to_string(&log).expect("should write shim log");
+    assert!(content.contains("stdout of home moonup install latest"));
+    assert!(!content.contains("stdout of moonup"));
+
+    // The recorded moonup is preferred over `$MOONUP_HOME/bin/moonup`
+    let recorded = fake_moonup(&ws.tempdir().path().join("owner"), "owning moonup");
+    fs::write(
+        ws.moonup_home().join(constant::MOONUP_EXE_FILE),
+        recorded.to_string_lossy().as_bytes(),
+    )
+    .expect("should record moonup");
+    let output = run_moon(&ws, &[("PATH", &path)]);
+    assert!(!output.status.success());
+    let content = fs::read_to_string(&log).expect("should write shim log");
+    assert!(content.contains("stdout of owning moonup install latest"));
+}
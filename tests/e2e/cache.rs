@@ -0,0 +1,175 @@
+use std::fs;
+
+use crate::util::TestWorkspace;
+
+#[test]
+fn test_cache_prune() {
+    let ws = TestWorkspace::new();
+    let downloads_dir = ws.moonup_home().join("downloads");
+
+    let sha256 = "65f77ae8d172385a19157f338ca63f6cdb836e1fce82751c2ea8d7e5c7991823";
+    let store_dir = downloads_dir.join("store").join("sha256");
+    fs::create_dir_all(&store_dir).expect("should create store directory");
+
+    let used_blob = store_dir.join(sha256);
+    let orphan_blob = store_dir.join("0".repeat(64));
+    fs::write(&used_blob, b"used").expect("should create used blob");
+    fs::write(&orphan_blob, b"orphan").expect("should create orphan blob");
+
+    // The component index references the used blob
+    let release_dir = downloads_dir.join("latest").join("0.10.0");
+    fs::create_dir_all(&release_dir).expect("should create release directory");
+    let component_index = release_dir.join("x86_64-unknown-linux.json");
+    fs::write(
+        &component_index,
+        format!(
+            r#"{{"version":2,"components":[{{"name":"toolchain","file":"moonbit.tar.gz","sha256":"{sha256}"}}]}}"#
+        ),
+    )
+    .expect("should create component index");
+    // An expired lockfile makes the index stale
+    fs::write(release_dir.join("x86_64-unknown-linux.json.lock"), "0")
+        .expect("should create lockfile");
+
+    // The toolchain using the blob is installed
+    let toolchain_dir = ws.moonup_home().join("toolchains").join("0.10.0");
+    fs::create_dir_all(toolchain_dir.join("bin")).expect("should create toolchain directory");
+
+    let output = ws
+        .cli()
+        .arg("cache")
+        .arg("prune")
+        .arg("--dry-run")
+        .output()
+        .expect("should run cache prune");
+    assert!(output.status.success());
+    assert!(orphan_blob.exists(), "dry run should not remove anything");
+
+    let output = ws
+        .cli()
+        .arg("cache")
+        .arg("prune")
+        .output()
+        .expect("should run cache prune");
+    assert!(output.status.success());
+    assert!(!orphan_blob.exists(), "orphan blob should be removed");
+    assert!(
+        used_blob.exists(),
+        "blob of installed toolchain should be kept"
+    );
+    assert!(
+        component_index.exists(),
+        "index of installed toolchain should be kept"
+    );
+
+    let output = ws
+        .cli()
+        .arg("cache")
+        .arg("prune")
+        .arg("--max-size")
+        .arg("0")
+        .arg("--keep-installed")
+        .output()
+        .expect("should run cache prune");
+    assert!(output.status.success());
+    assert!(
+        used_blob.exists(),
+        "blob of installed toolchain should be kept"
+    );
+
+    let output = ws
+        .cli()
+        .arg("cache")
+        .arg("prune")
+        .arg("--max-size")
+        .arg("0")
+        .output()
+        .expect("should run cache prune");
+    assert!(output.status.success());
+    assert!(!used_blob.exists(), "all entries should be removed");
+    assert!(!release_dir.exists(), "empty directories should be removed");
+}
+
+#[test]
+fn test_uninstall_keeps_shared_downloads() {
+    let ws = TestWorkspace::new();
+    let store_dir = ws.moonup_home().join("downloads/store/sha256");
+    fs::create_dir_all(&store_dir).expect("should create store directory");
+
+    let shared = "65f77ae8d172385a19157f338ca63f6cdb836e1fce82751c2ea8d7e5c7991823";
+    let own = "cf1b56aea8868e856e3345d4e8ed0fd2cd10907170a0bcbf4494ec532abb3e86";
+    fs::write(store_dir.join(shared), b"shared").expect("should create shared blob");
+    fs::write(store_dir.join(own), b"own").expect("should create own blob");
+
+    // `latest` and the version it points to share the toolchain component
+    let install = |name: &str, sha256s: &[&str]| {
+        let toolchain_dir = ws.moonup_home().join("toolchains").join(name);
+        fs::create_dir_all(toolchain_dir.join("bin")).expect("should create toolchain directory");
+        let components = sha256s
+            .iter()
+            .map(|sha256| {
+                format!(r#"{{"name":"toolchain","file":"moonbit.tar.gz","sha256":"{sha256}"}}"#)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        fs::write(
+            toolchain_dir.join("receipt.json"),
+            format!(
+                r#"{{"spec":"{name}","release":{{"version":"0.10.0"}},"distServer":"https://moonup.csu.moe/v3","target":"x86_64-unknown-linux","components":[{components}],"moonupVersion":"0.0.0","installedAt":"2025-01-01T00:00:00Z"}}"#
+            ),
+        )
+        .expect("should create receipt");
+    };
+    install("latest", &[shared, own]);
+    install("0.10.0", &[shared]);
+
+    let output = ws
+        .cli()
+        .args(["uninstall", "latest"])
+        .output()
+        .expect("should run uninstall");
+    assert!(output.status.success(), "{output:?}");
+    assert!(!ws.moonup_home().join("toolchains/latest").exists());
+    assert!(
+        store_dir.join(shared).is_file(),
+        "blob of the other toolchain should be kept"
+    );
+    assert!(
+        !store_dir.join(own).exists(),
+        "unused blob should be removed"
+    );
+
+    let output = ws
+        .cli()
+        .args(["uninstall", "0.10.0"])
+        .output()
+        .expect("should run uninstall");
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        !store_dir.join(shared).exists(),
+        "unused blob should be removed"
+    );
+}
+
+#[test]
+fn test_cache_prune_invalid_age() {
+    let ws = TestWorkspace::new();
+
+    let prune = |age: &str| {
+        let output = ws
+            .cli()
+            .args(["cache", "prune", "--older-than", age])
+            .output()
+            .expect("should run cache prune");
+        assert!(!output.status.success(), "invalid age should be rejected");
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    let stderr = prune("99999999999999999w");
+    assert!(
+        stderr.contains("duration '99999999999999999w' is too large"),
+        "{stderr}"
+    );
+    let stderr = prune("3y");
+    assert!(stderr.contains("invalid duration unit in '3y'"), "{stderr}");
+}
@@ -0,0 +1,50 @@
+use std::fs;
+
+use crate::util::TestWorkspace;
+
+#[test]
+fn test_doctor() {
+    let ws = TestWorkspace::new();
+    let moon_home_bin = ws.moon_home().join("bin");
+    fs::create_dir_all(&moon_home_bin).expect("should create bin directory");
+    fs::write(
+        moon_home_bin.join(format!("moon{}", std::env::consts::EXE_SUFFIX)),
+        b"stale shim moonup-shim-version:0.0.1\0",
+    )
+    .expect("should create stale shim");
+
+    let output = ws
+        .cli()
+        .arg("doctor")
+        .env("PATH", &moon_home_bin)
+        .env("MOONUP_DIST_SERVER", "http://127.0.0.1:1")
+        .output()
+        .expect("should run doctor");
+    assert!(!output.status.success(), "doctor should report errors");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("is on PATH"), "should find shims on PATH");
+    assert!(
+        stdout.contains("1 shim(s) differ from the current moonup-shim"),
+        "should report the stale shim"
+    );
+    assert!(
+        stdout.contains(&format!(
+            "moon{} (moonup 0.0.1)",
+            std::env::consts::EXE_SUFFIX
+        )),
+        "should report the version of the stale shim"
+    );
+    assert!(
+        stdout.contains("run `moonup shims rebuild` to pour the shims again"),
+        "should suggest repairing the shims"
+    );
+    assert!(
+        stdout.contains("active toolchain 'latest' is not installed"),
+        "should report the missing active toolchain"
+    );
+    assert!(
+        stdout.contains("distribution server 'http://127.0.0.1:1' is unreachable"),
+        "should report the unreachable distribution server"
+    );
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+
+use moonup::constant;
+
+use crate::util::TestWorkspace;
+
+/// `SIGTERM` is 15 on all Unix platforms
+const SIGTERM: i32 = 15;
+
+#[test]
+fn test_shim_replaces_itself() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "echo $$");
+
+    let child = ws
+        .cmd(ws.shim("moon"))
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("should spawn shim");
+    let pid = child.id();
+    let output = child.wait_with_output().expect("should wait for shim");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        pid.to_string()
+    );
+}
+
+#[test]
+fn test_shim_exit_status() {
+    let ws = TestWorkspace::new();
+    let shim = ws.shim("moon");
+
+    ws.fake_toolchain("latest", &["moon"], "exit 42");
+    let status = ws.cmd(&shim).status().expect("should run shim");
+    assert_eq!(status.code(), Some(42));
+
+    ws.fake_toolchain("latest", &["moon"], "kill -TERM $$");
+    let status = ws.cmd(&shim).status().expect("should run shim");
+    assert_eq!(status.signal(), Some(SIGTERM));
+}
+
+#[test]
+fn test_run_exit_status() {
+    let ws = TestWorkspace::new();
+
+    ws.fake_toolchain("latest", &["moon"], "kill -TERM $$");
+    let status = ws
+        .cli()
+        .args(["run", "latest", "moon"])
+        .status()
+        .expect("should run moonup run");
+    assert_eq!(status.signal(), Some(SIGTERM));
+}
+
+#[test]
+fn test_shim_reraises_signal_of_child() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "exit 0");
+
+    // `moon upgrade` runs `moonup update` as a child process
+    let moonup = ws.tempdir().path().join("moonup");
+    fs::write(&moonup, "#!/bin/sh\nkill -TERM $$\n").expect("should create moonup");
+    fs::set_permissions(&moonup, fs::Permissions::from_mode(0o755))
+        .expect("should mark moonup as executable");
+    fs::write(
+        ws.moonup_home().join(constant::MOONUP_EXE_FILE),
+        moonup.to_string_lossy().as_bytes(),
+    )
+    .expect("should record moonup");
+
+    let status = ws
+        .cmd(ws.shim("moon"))
+        .arg("upgrade")
+        .status()
+        .expect("should run shim");
+    assert_eq!(status.signal(), Some(SIGTERM));
+}
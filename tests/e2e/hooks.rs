@@ -0,0 +1,118 @@
+use std::fs;
+
+use crate::util::TestWorkspace;
+
+#[test]
+fn test_uninstall_hooks() {
+    let ws = TestWorkspace::new();
+    let version = "0.1.20241231+ba15a9a4e";
+    let toolchain_dir = ws.moonup_home().join("toolchains").join(version);
+    fs::create_dir_all(toolchain_dir.join("bin")).expect("should create toolchain directory");
+
+    let log = ws.tempdir().path().join("hooks.log");
+    fs::write(
+        ws.moonup_home().join("settings.toml"),
+        format!(
+            r#"
+[hooks]
+pre-uninstall = 'echo "$MOONUP_HOOK $MOONUP_HOOK_TOOLCHAIN $MOONUP_HOOK_PATH" >> {log}'
+post-uninstall = 'echo "$MOONUP_HOOK $MOONUP_HOOK_TOOLCHAIN" >> {log}'
+"#,
+            log = log.display()
+        ),
+    )
+    .expect("should create settings");
+
+    let output = ws
+        .cli()
+        .args(["uninstall", version, "--keep-cache"])
+        .output()
+        .expect("should run uninstall");
+    assert!(output.status.success());
+    assert!(!toolchain_dir.exists());
+
+    let log = fs::read_to_string(&log).expect("hooks should write the log");
+    assert_eq!(
+        log,
+        format!(
+            "pre-uninstall {version} {}\npost-uninstall {version}\n",
+            toolchain_dir.display()
+        )
+    );
+}
+
+#[test]
+fn test_failed_pre_hook_aborts() {
+    let ws = TestWorkspace::new();
+    let toolchain_dir = ws.moonup_home().join("toolchains").join("latest");
+    fs::create_dir_all(toolchain_dir.join("bin")).expect("should create toolchain directory");
+
+    fs::write(
+        ws.moonup_home().join("settings.toml"),
+        "[hooks]\npre-uninstall = 'exit 3'\n",
+    )
+    .expect("should create settings");
+
+    let output = ws
+        .cli()
+        .args(["uninstall", "latest", "--keep-cache"])
+        .output()
+        .expect("should run uninstall");
+    assert!(
+        !output.status.success(),
+        "failed hook should fail uninstall"
+    );
+    assert!(toolchain_dir.exists(), "toolchain should be kept");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("pre-uninstall hook `exit 3` failed"),
+        "should report the failed hook"
+    );
+}
+
+#[test]
+fn test_failed_post_hook_continues() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "");
+    ws.fake_toolchain("nightly", &["moon"], "");
+    ws.fake_toolchain("0.6.30", &["mooncake"], "");
+    let output = ws
+        .cli()
+        .args(["shims", "rebuild"])
+        .output()
+        .expect("should run shims rebuild");
+    assert!(output.status.success());
+    let mooncake = ws.moon_home().join("bin").join("mooncake");
+    assert!(mooncake.is_file());
+
+    fs::write(
+        ws.moonup_home().join("settings.toml"),
+        "[hooks]\npost-uninstall = 'exit 3'\n",
+    )
+    .expect("should create settings");
+
+    let output = ws
+        .cli()
+        .args(["uninstall", "0.6.30", "nightly", "--keep-cache"])
+        .output()
+        .expect("should run uninstall");
+    assert!(
+        !output.status.success(),
+        "failed hook should fail uninstall"
+    );
+    for name in ["0.6.30", "nightly"] {
+        assert!(
+            !ws.moonup_home().join("toolchains").join(name).exists(),
+            "all toolchains should be uninstalled"
+        );
+    }
+    assert!(!mooncake.exists(), "stale shims should be removed");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("2 hooks failed"), "{stderr}");
+    assert!(
+        stderr.contains("post-uninstall hook `exit 3` failed"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,78 @@
+use std::fs;
+
+use crate::util::TestWorkspace;
+
+#[test]
+fn test_list_json() {
+    let ws = TestWorkspace::new();
+    let toolchains_dir = ws.moonup_home().join("toolchains");
+
+    // A healthy toolchain with a bundled core library
+    let latest_dir = toolchains_dir.join("latest");
+    fs::create_dir_all(latest_dir.join("bin")).expect("should create bin directory");
+    fs::write(
+        latest_dir
+            .join("bin")
+            .join(format!("moon{}", std::env::consts::EXE_SUFFIX)),
+        b"moon",
+    )
+    .expect("should create moon executable");
+    let bundle_dir = latest_dir
+        .join("lib")
+        .join("core")
+        .join("_build")
+        .join("wasm-gc")
+        .join("release")
+        .join("bundle");
+    fs::create_dir_all(&bundle_dir).expect("should create bundle directory");
+    fs::write(latest_dir.join("version"), "0.1.20250108+1234\n")
+        .expect("should create version stub");
+
+    // A broken toolchain without a `bin` directory
+    fs::create_dir_all(toolchains_dir.join("0.1.20241231+ba15a9a4e"))
+        .expect("should create toolchain directory");
+
+    let output = ws
+        .cli()
+        .arg("list")
+        .arg("--json")
+        .output()
+        .expect("should run list --json");
+    assert!(output.status.success());
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("should print valid JSON");
+    let toolchains = json["toolchains"]
+        .as_array()
+        .expect("should list toolchains");
+    assert_eq!(toolchains.len(), 2);
+
+    let find = |name: &str| {
+        toolchains
+            .iter()
+            .find(|t| t["name"] == name)
+            .expect("should list toolchain")
+    };
+
+    let latest = find("latest");
+    assert_eq!(latest["version"], "0.1.20250108+1234");
+    assert_eq!(latest["isActive"], true);
+    assert_eq!(latest["hasMoon"], true);
+    assert_eq!(latest["coreBundled"], true);
+    assert_eq!(latest["broken"], false);
+    assert!(latest["size"].as_u64().unwrap_or_default() > 0);
+
+    let versioned = find("0.1.20241231+ba15a9a4e");
+    assert_eq!(versioned["version"], "0.1.20241231+ba15a9a4e");
+    assert_eq!(versioned["broken"], true);
+
+    let output = ws
+        .cli()
+        .arg("list")
+        .arg("--long")
+        .output()
+        .expect("should run list --long");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("broken (missing bin/)"));
+}
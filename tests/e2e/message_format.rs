@@ -0,0 +1,100 @@
+use std::fs;
+
+use crate::util::TestWorkspace;
+
+fn json_events(stdout: &[u8]) -> Vec<serde_json::Value> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("should print one JSON event per line"))
+        .collect()
+}
+
+#[test]
+fn test_message_format_json() {
+    let ws = TestWorkspace::new();
+    let toolchain_dir = ws.moonup_home().join("toolchains").join("latest");
+    fs::create_dir_all(toolchain_dir.join("bin")).expect("should create toolchain directory");
+
+    let output = ws
+        .cli()
+        .args([
+            "uninstall",
+            "latest",
+            "--keep-cache",
+            "--message-format",
+            "json",
+        ])
+        .output()
+        .expect("should run uninstall");
+    assert!(output.status.success());
+
+    let events = json_events(&output.stdout);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "uninstalled");
+    assert_eq!(events[0]["spec"], "latest");
+    assert!(!toolchain_dir.exists());
+
+    let output = ws
+        .cli()
+        .args(["uninstall", "--clear", "--message-format", "json"])
+        .output()
+        .expect("should run uninstall --clear");
+    assert!(output.status.success());
+
+    let events = json_events(&output.stdout);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "cache_cleared");
+}
+
+#[test]
+fn test_message_format_json_error() {
+    let ws = TestWorkspace::new();
+
+    let output = ws
+        .cli()
+        .args(["shims", "rebuild", "--message-format", "json"])
+        .env(moonup::constant::ENVNAME_MOONUP_SHIM_MODE, "junction")
+        .output()
+        .expect("should run shims rebuild");
+    assert!(!output.status.success());
+
+    let events = json_events(&output.stdout);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "error");
+    assert!(
+        events[0]["message"]
+            .as_str()
+            .is_some_and(|m| m.contains("invalid shim mode 'junction'")),
+        "{}",
+        events[0]
+    );
+
+    // Commands without events don't accept the message format
+    let output = ws
+        .cli()
+        .args(["verify", "latest", "--message-format", "json"])
+        .output()
+        .expect("should run verify");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_message_format_json_install_error() {
+    let ws = TestWorkspace::new();
+
+    // No toolchain given and none pinned
+    let output = ws
+        .cli()
+        .args(["install", "--message-format", "json"])
+        .output()
+        .expect("should run install");
+    assert!(!output.status.success());
+
+    let events = json_events(&output.stdout);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["event"], "error");
+    assert_eq!(
+        events[0]["message"],
+        "No toolchain specified and no pinned toolchain found"
+    );
+}
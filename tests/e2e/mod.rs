@@ -1,2 +1,21 @@
+mod auth;
+mod auto_install;
+mod cache;
+mod doctor;
+#[cfg(unix)]
+mod exec;
 mod flow;
+#[cfg(unix)]
+mod hooks;
+mod list;
+mod message_format;
+mod network;
+#[cfg(unix)]
+mod not_found;
+#[cfg(unix)]
+mod rebuild;
+mod repair;
 mod shim;
+mod verify;
+#[cfg(unix)]
+mod which;
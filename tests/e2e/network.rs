@@ -0,0 +1,120 @@
+use mockito::Server;
+use moonup::constant;
+use std::fs;
+
+use crate::util::TestWorkspace;
+
+#[test]
+fn test_proxy_from_settings() {
+    let ws = TestWorkspace::new();
+    let mut proxy = Server::new();
+
+    // Requests to the dist server are sent to the proxy in absolute form
+    let mock = proxy
+        .mock("GET", "/v3/index.json")
+        .with_body_from_file("tests/fixtures/dist_server/index.json")
+        .with_header("content-type", "application/json")
+        .create();
+
+    fs::create_dir_all(ws.moonup_home()).expect("should create moonup home");
+    fs::write(
+        ws.moonup_home().join("settings.toml"),
+        format!("[network]\nproxy = \"{}\"\n", proxy.url()),
+    )
+    .expect("should create settings");
+
+    let output = ws
+        .cli()
+        .env(
+            constant::ENVNAME_MOONUP_DIST_SERVER,
+            "http://dist.invalid/v3",
+        )
+        .args(["install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Available channel(s):"));
+    mock.assert();
+}
+
+#[test]
+fn test_missing_ca_bundle() {
+    let ws = TestWorkspace::new();
+    let ca_bundle = ws.tempdir().path().join("missing.pem");
+
+    let output = ws
+        .cli()
+        .env(constant::ENVNAME_MOONUP_CA_BUNDLE, &ca_bundle)
+        .args(["install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("failed to read CA bundle"));
+}
+
+#[test]
+fn test_retry_after() {
+    let ws = TestWorkspace::new();
+    let mut server = Server::new();
+
+    let unavailable = server
+        .mock("GET", "/index.json")
+        .with_status(503)
+        .with_header("retry-after", "0")
+        .expect(1)
+        .create();
+    let available = server
+        .mock("GET", "/index.json")
+        .with_body_from_file("tests/fixtures/dist_server/index.json")
+        .with_header("content-type", "application/json")
+        .create();
+
+    let output = ws
+        .cli()
+        .env(constant::ENVNAME_MOONUP_DIST_SERVER, server.url())
+        .env(constant::ENVNAME_MOONUP_RETRY_MIN_DELAY, "10m")
+        .env(constant::ENVNAME_MOONUP_RETRY_MAX_DELAY, "10m")
+        .args(["install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    unavailable.assert();
+    available.assert();
+}
+
+#[test]
+fn test_invalid_network_policy() {
+    let ws = TestWorkspace::new();
+
+    let output = ws
+        .cli()
+        .env(constant::ENVNAME_MOONUP_READ_TIMEOUT, "5 minutes")
+        .args(["install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid MOONUP_READ_TIMEOUT"));
+
+    fs::create_dir_all(ws.moonup_home()).expect("should create moonup home");
+    fs::write(
+        ws.moonup_home().join("settings.toml"),
+        "[network]\nretry-min-delay = \"2m\"\nretry-max-delay = \"1m\"\n",
+    )
+    .expect("should create settings");
+
+    let output = ws
+        .cli()
+        .args(["install", "--list-available"])
+        .output()
+        .expect("should run install --list-available");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exceeds the maximum retry delay"));
+}
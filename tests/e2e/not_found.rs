@@ -0,0 +1,40 @@
+use crate::util::TestWorkspace;
+
+#[test]
+fn test_command_available_in_other_toolchains() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "");
+    ws.fake_toolchain("nightly", &["moon", "mooncake"], "");
+    ws.fake_toolchain("0.6.30", &["moon", "internal/mooncake"], "");
+
+    let output = ws
+        .cmd(ws.shim("mooncake"))
+        .env("MOONUP_TOOLCHAIN_SPEC", "latest")
+        .output()
+        .expect("should run shim");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Command 'mooncake' not found in toolchain 'latest'"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(
+            "mooncake is available in 0.6.30, nightly; try `moonup run 0.6.30 mooncake` or `mooncake +0.6.30`"
+        ),
+        "{stderr}"
+    );
+
+    let output = ws
+        .cli()
+        .args(["run", "nightly", "moonfmt"])
+        .output()
+        .expect("should run moonup run");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Command 'moonfmt' not found in toolchain"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("is available in"), "{stderr}");
+}
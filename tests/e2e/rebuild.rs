@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::Path;
+
+use moonup::constant;
+
+use crate::util::TestWorkspace;
+
+fn write_bin(path: &Path, content: &[u8]) {
+    fs::create_dir_all(path.parent().unwrap()).expect("should create bin directory");
+    fs::write(path, content).expect("should create executable");
+}
+
+fn rebuild(ws: &TestWorkspace) -> serde_json::Value {
+    rebuild_with(ws, "")
+}
+
+fn rebuild_with(ws: &TestWorkspace, mode: &str) -> serde_json::Value {
+    let output = ws
+        .cli()
+        .args(["shims", "rebuild", "--message-format", "json"])
+        .env(constant::ENVNAME_MOONUP_SHIM_MODE, mode)
+        .output()
+        .expect("should run shims rebuild");
+    assert!(output.status.success(), "{output:?}");
+    serde_json::from_slice(&output.stdout).expect("should print a JSON event")
+}
+
+#[test]
+fn test_shims_rebuild() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon", "moonc", "internal/tcc"], "");
+    ws.fake_toolchain("nightly", &["moon", "mooncake"], "");
+
+    let shim = fs::read(insta_cmd::get_cargo_bin("moonup-shim")).expect("should read shim");
+    let moon_home_bin = ws.moon_home().join("bin");
+    // An outdated shim, a stale recorded shim, a stale unrecorded shim copy
+    // and a binary installed by the user
+    write_bin(&moon_home_bin.join("moon"), b"old shim");
+    write_bin(&moon_home_bin.join("moonfmt"), b"old shim");
+    write_bin(&moon_home_bin.join("internal/moonrun"), &shim);
+    write_bin(&moon_home_bin.join("my-tool"), b"user binary");
+    fs::create_dir_all(ws.moonup_home()).expect("should create moonup home");
+    fs::write(
+        ws.moonup_home().join(constant::SHIMS_FILE),
+        "moon\nmoonfmt\n",
+    )
+    .expect("should record shims");
+
+    let event = rebuild(&ws);
+    assert_eq!(event["event"], "shims_rebuilt");
+    assert_eq!(
+        event["created"],
+        serde_json::json!(["internal/tcc", "moonc", "mooncake"])
+    );
+    assert_eq!(event["refreshed"], serde_json::json!(["moon"]));
+    let mut removed = event["removed"]
+        .as_array()
+        .expect("should list removed shims")
+        .clone();
+    removed.sort_by_key(|v| v.to_string());
+    assert_eq!(
+        removed,
+        vec![
+            serde_json::json!("internal/moonrun"),
+            serde_json::json!("moonfmt")
+        ]
+    );
+
+    for name in ["moon", "moonc", "mooncake", "internal/tcc"] {
+        let path = moon_home_bin.join(name);
+        assert_eq!(fs::read(&path).ok().as_ref(), Some(&shim), "{name}");
+    }
+    assert!(!moon_home_bin.join("moonfmt").exists());
+    assert!(!moon_home_bin.join("internal/moonrun").exists());
+    assert_eq!(
+        fs::read(moon_home_bin.join("my-tool")).expect("should keep user binary"),
+        b"user binary"
+    );
+
+    let recorded = fs::read_to_string(ws.moonup_home().join(constant::SHIMS_FILE))
+        .expect("should record shims");
+    assert_eq!(recorded, "internal/tcc\nmoon\nmoonc\nmooncake\n");
+
+    // Nothing to do the second time
+    let event = rebuild(&ws);
+    assert_eq!(event["created"], serde_json::json!([]));
+    assert_eq!(event["refreshed"], serde_json::json!([]));
+    assert_eq!(event["removed"], serde_json::json!([]));
+
+    let output = ws
+        .cli()
+        .args(["shims", "rebuild"])
+        .output()
+        .expect("should run shims rebuild");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Shims are up to date"));
+}
+
+#[test]
+fn test_uninstall_removes_stale_shims() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon", "moonc"], "");
+    ws.fake_toolchain("nightly", &["moon", "mooncake"], "");
+    rebuild(&ws);
+
+    let moon_home_bin = ws.moon_home().join("bin");
+    write_bin(&moon_home_bin.join("my-tool"), b"user binary");
+
+    let output = ws
+        .cli()
+        .args(["uninstall", "nightly", "--keep-cache"])
+        .output()
+        .expect("should run uninstall");
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Rebuilt shims: 0 created, 0 refreshed, 1 removed"),
+        "{stdout}"
+    );
+
+    assert!(moon_home_bin.join("moon").is_file());
+    assert!(moon_home_bin.join("moonc").is_file());
+    assert!(!moon_home_bin.join("mooncake").exists());
+    assert!(moon_home_bin.join("my-tool").is_file());
+
+    // Shims are kept for auto-install once the last toolchain is gone
+    let output = ws
+        .cli()
+        .args(["uninstall", "latest", "--keep-cache"])
+        .output()
+        .expect("should run uninstall");
+    assert!(output.status.success(), "{output:?}");
+    assert!(moon_home_bin.join("moon").is_file());
+    assert!(moon_home_bin.join("moonc").is_file());
+}
+
+#[test]
+fn test_shim_version_marker() {
+    let shim = insta_cmd::get_cargo_bin("moonup-shim");
+    let version = moonup::shim::shim_version(&shim).expect("should read shim");
+    assert_eq!(version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+
+    let ws = TestWorkspace::new();
+    let file = ws.tempdir().path().join("not-a-shim");
+    fs::write(&file, b"moonup-shim-version:\0").expect("should create file");
+    assert_eq!(moonup::shim::shim_version(&file).ok().flatten(), None);
+}
+
+#[test]
+fn test_shims_rebuild_refreshes_old_shims() {
+    let ws = TestWorkspace::new();
+    let moon_home_bin = ws.moon_home().join("bin");
+    let old_shim = b"old shim moonup-shim-version:0.0.1\0";
+    write_bin(&moon_home_bin.join("moon"), old_shim);
+    write_bin(&moon_home_bin.join("moonc"), old_shim);
+    write_bin(&moon_home_bin.join("my-tool"), b"user binary");
+
+    // Shims are refreshed, not removed, without any installed toolchain
+    let event = rebuild(&ws);
+    assert_eq!(event["refreshed"], serde_json::json!(["moon", "moonc"]));
+    assert_eq!(event["removed"], serde_json::json!([]));
+    let version =
+        moonup::shim::shim_version(&moon_home_bin.join("moon")).expect("should read shim");
+    assert_eq!(version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+
+    // Shims of older versions are removed once no toolchain provides them
+    write_bin(&moon_home_bin.join("moonc"), old_shim);
+    ws.fake_toolchain("latest", &["moon"], "");
+    let event = rebuild(&ws);
+    assert_eq!(event["removed"], serde_json::json!(["moonc"]));
+    assert!(moon_home_bin.join("my-tool").is_file());
+}
+
+#[test]
+fn test_shims_link_modes() {
+    use std::os::unix::fs::MetadataExt;
+
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moonc"], "");
+    ws.fake_toolchain("latest", &["moon"], "echo \"moon $@\"");
+
+    let shim = insta_cmd::get_cargo_bin("moonup-shim")
+        .canonicalize()
+        .expect("should resolve shim");
+    let moon_home_bin = ws.moon_home().join("bin");
+    let run_moon = || {
+        let output = ws
+            .cmd(moon_home_bin.join("moon"))
+            .arg("version")
+            .output()
+            .expect("should run moon shim");
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    // Symlinked shims dispatch on the name they are invoked by
+    let event = rebuild_with(&ws, "symlink");
+    assert_eq!(event["created"], serde_json::json!(["moon", "moonc"]));
+    for name in ["moon", "moonc"] {
+        let link = fs::read_link(moon_home_bin.join(name)).expect("should be a symlink");
+        assert_eq!(link, shim);
+    }
+    assert_eq!(run_moon(), "moon version");
+    let event = rebuild_with(&ws, "symlink");
+    assert_eq!(event["refreshed"], serde_json::json!([]));
+
+    // Switching the mode pours the shims again
+    fs::write(
+        ws.moonup_home().join(constant::SETTINGS_FILE),
+        "shim-mode = \"hardlink\"\n",
+    )
+    .expect("should write settings");
+    let event = rebuild(&ws);
+    assert_eq!(event["refreshed"], serde_json::json!(["moon", "moonc"]));
+    let metadata = fs::symlink_metadata(moon_home_bin.join("moon")).expect("should stat shim");
+    let shim_metadata = fs::metadata(&shim).expect("should stat shim");
+    assert!(metadata.is_file());
+    assert_eq!(
+        (metadata.dev(), metadata.ino()),
+        (shim_metadata.dev(), shim_metadata.ino())
+    );
+    assert_eq!(run_moon(), "moon version");
+
+    let event = rebuild_with(&ws, "copy");
+    assert_eq!(event["refreshed"], serde_json::json!(["moon", "moonc"]));
+    let metadata = fs::symlink_metadata(moon_home_bin.join("moon")).expect("should stat shim");
+    assert!(metadata.is_file());
+    assert_ne!(metadata.ino(), shim_metadata.ino());
+    assert_eq!(run_moon(), "moon version");
+
+    let output = ws
+        .cli()
+        .args(["shims", "rebuild"])
+        .env(constant::ENVNAME_MOONUP_SHIM_MODE, "junction")
+        .output()
+        .expect("should run shims rebuild");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("invalid shim mode 'junction', expected one of copy, symlink, hardlink")
+    );
+}
@@ -0,0 +1,89 @@
+use moonup::constant;
+use std::fs;
+use std::path::Path;
+
+use crate::util::TestWorkspace;
+
+#[cfg(unix)]
+fn write_moon(toolchain_dir: &Path, exit_code: i32) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let moon = toolchain_dir.join("bin").join("moon");
+    fs::write(
+        &moon,
+        format!("#!/bin/sh\necho \"bundle output\"\nexit {exit_code}\n"),
+    )
+    .expect("should create moon executable");
+    fs::set_permissions(&moon, fs::Permissions::from_mode(0o755))
+        .expect("should mark moon as executable");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_repair_incomplete_install() {
+    let ws = TestWorkspace::new();
+
+    let version = "0.1.20241231+ba15a9a4e";
+    let toolchain_dir = ws.moonup_home().join("toolchains").join(version);
+    fs::create_dir_all(toolchain_dir.join("bin")).expect("should create bin directory");
+    fs::create_dir_all(toolchain_dir.join("lib").join("core"))
+        .expect("should create core directory");
+    fs::write(
+        toolchain_dir.join("receipt.json"),
+        format!(
+            r#"{{"spec":"{version}","release":{{"version":"{version}"}},"distServer":"https://moonup.csu.moe/v3","target":"x86_64-unknown-linux","components":[],"moonupVersion":"0.0.0","installedAt":"2025-01-01T00:00:00Z"}}"#
+        ),
+    )
+    .expect("should create receipt");
+
+    // A failing bundler marks the toolchain as incomplete
+    write_moon(&toolchain_dir, 1);
+    let output = ws
+        .cli()
+        .arg("repair")
+        .arg(version)
+        .output()
+        .expect("should run repair");
+    assert!(!output.status.success(), "failed bundling should fail");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("bundle output"),
+        "should report bundler output"
+    );
+    assert!(stderr.contains("moonup repair"), "should suggest repair");
+    let marker = toolchain_dir.join("incomplete");
+    assert!(marker.is_file(), "should mark toolchain as incomplete");
+
+    let output = ws
+        .cli()
+        .args(["list", "--json"])
+        .output()
+        .expect("should run list --json");
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("should print valid JSON");
+    assert_eq!(json["toolchains"][0]["incomplete"], true);
+
+    // Repairing with a working bundler finishes the install
+    write_moon(&toolchain_dir, 0);
+    let output = ws
+        .cli()
+        .arg("repair")
+        .arg(version)
+        .output()
+        .expect("should run repair");
+    assert!(output.status.success(), "repair should succeed");
+    assert!(!marker.exists(), "should remove the incomplete marker");
+    assert!(
+        ws.moon_home().join("bin").join("moon").is_file(),
+        "should pour the moon shim"
+    );
+
+    let recorded = fs::read_to_string(ws.moonup_home().join(constant::MOONUP_EXE_FILE))
+        .expect("should record the moonup executable");
+    assert_eq!(
+        Path::new(&recorded),
+        ws.moonup()
+            .canonicalize()
+            .expect("should canonicalize moonup")
+    );
+}
@@ -0,0 +1,74 @@
+use std::fs;
+
+use crate::util::TestWorkspace;
+
+#[test]
+fn test_verify_repair() {
+    let ws = TestWorkspace::new();
+
+    let version = "0.1.20241231+ba15a9a4e";
+    let sha256 = "65f77ae8d172385a19157f338ca63f6cdb836e1fce82751c2ea8d7e5c7991823";
+
+    // The cached download of the toolchain
+    let store_dir = ws
+        .moonup_home()
+        .join("downloads")
+        .join("store")
+        .join("sha256");
+    fs::create_dir_all(&store_dir).expect("should create store directory");
+    fs::write(
+        store_dir.join(sha256),
+        include_bytes!("../fixtures/archive/test.tar.gz"),
+    )
+    .expect("should create cached download");
+
+    // The toolchain installed from the cached download
+    let toolchain_dir = ws.moonup_home().join("toolchains").join(version);
+    fs::create_dir_all(&toolchain_dir).expect("should create toolchain directory");
+    fs::write(toolchain_dir.join("world.txt"), b"").expect("should create toolchain file");
+    fs::write(
+        toolchain_dir.join("manifest.json"),
+        r#"{"files":[{"path":"world.txt","size":0,"sha256":"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"}]}"#,
+    )
+    .expect("should create manifest");
+    fs::write(
+        toolchain_dir.join("receipt.json"),
+        format!(
+            r#"{{"spec":"{version}","release":{{"version":"{version}"}},"distServer":"https://moonup.csu.moe/v3","target":"x86_64-unknown-linux","components":[{{"name":"toolchain","file":"test.tar.gz","sha256":"{sha256}"}}],"moonupVersion":"0.0.0","installedAt":"2025-01-01T00:00:00Z"}}"#
+        ),
+    )
+    .expect("should create receipt");
+
+    let output = ws.cli().arg("verify").output().expect("should run verify");
+    assert!(output.status.success(), "fresh install should be intact");
+
+    fs::write(toolchain_dir.join("world.txt"), b"tampered").expect("should tamper file");
+    fs::write(toolchain_dir.join("extra.txt"), b"extra").expect("should add extra file");
+
+    let output = ws
+        .cli()
+        .arg("verify")
+        .arg(version)
+        .output()
+        .expect("should run verify");
+    assert!(!output.status.success(), "tampered install should fail");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("world.txt"), "should report modified file");
+    assert!(stdout.contains("extra.txt"), "should report extra file");
+
+    let output = ws
+        .cli()
+        .arg("verify")
+        .arg("--repair")
+        .output()
+        .expect("should run verify --repair");
+    assert!(output.status.success(), "repair should succeed");
+    assert_eq!(
+        fs::read(toolchain_dir.join("world.txt")).expect("should read toolchain file"),
+        b""
+    );
+    assert!(
+        !toolchain_dir.join("extra.txt").exists(),
+        "extra file should be removed"
+    );
+}
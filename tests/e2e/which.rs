@@ -0,0 +1,166 @@
+use std::fs;
+use std::process::Output;
+
+use moonup::constant;
+
+use crate::util::TestWorkspace;
+
+fn which_output(ws: &TestWorkspace, args: &[&str]) -> Output {
+    ws.cli()
+        .arg("which")
+        .args(args)
+        .env(constant::ENVNAME_MOONUP_TOOLCHAIN_SPEC, "latest")
+        .output()
+        .expect("should run which")
+}
+
+fn which(ws: &TestWorkspace, args: &[&str]) -> (String, String) {
+    let output = which_output(ws, args);
+    assert!(output.status.success(), "{output:?}");
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn test_which_toolchain() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "");
+    ws.fake_toolchain("nightly", &["moon", "mooncake"], "");
+    let toolchains = ws.moonup_home().join("toolchains");
+
+    let (stdout, _) = which(&ws, &["moon"]);
+    assert_eq!(
+        stdout.trim(),
+        toolchains.join("latest/bin/moon").to_string_lossy()
+    );
+
+    let (stdout, _) = which(&ws, &["--toolchain", "nightly", "mooncake"]);
+    assert_eq!(
+        stdout.trim(),
+        toolchains.join("nightly/bin/mooncake").to_string_lossy()
+    );
+
+    let output = which_output(&ws, &["mooncake"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Command 'mooncake' not found in toolchain 'latest'"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("try `moonup run nightly mooncake`"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_which_env() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "");
+
+    let (stdout, _) = which(&ws, &["--env", "moon"]);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(lines.contains(&"MOONUP_TOOLCHAIN_SPEC=latest"), "{stdout}");
+
+    let (stdout, _) = which(&ws, &["--env", "--toolchain", "latest", "moon"]);
+    assert!(stdout.contains("MOONUP_TOOLCHAIN_SPEC=latest"), "{stdout}");
+}
+
+#[test]
+fn test_which_all() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "");
+    ws.fake_toolchain("nightly", &["moon", "mooncake"], "");
+    let toolchains = ws.moonup_home().join("toolchains");
+
+    let (stdout, _) = which(&ws, &["--all", "mooncake"]);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(lines.contains(&"latest: not found"), "{stdout}");
+    let nightly = format!(
+        "nightly: {}",
+        toolchains.join("nightly/bin/mooncake").to_string_lossy()
+    );
+    assert!(lines.contains(&nightly.as_str()), "{stdout}");
+
+    let output = ws
+        .cli()
+        .args(["which", "--all", "--toolchain", "latest", "moon"])
+        .output()
+        .expect("should run which");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_which_json() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "");
+    ws.fake_toolchain("nightly", &["moon", "mooncake"], "");
+    let toolchains = ws.moonup_home().join("toolchains");
+
+    let (stdout, _) = which(&ws, &["--json", "moon"]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).expect("should print JSON");
+    assert_eq!(json["toolchain"], "latest");
+    assert_eq!(json["command"], "moon");
+    assert_eq!(json["found"], serde_json::json!(true));
+    assert_eq!(
+        json["program"],
+        serde_json::json!(toolchains.join("latest/bin/moon"))
+    );
+    assert_eq!(json["args"], serde_json::json!([]));
+    assert_eq!(json["env"]["MOONUP_TOOLCHAIN_SPEC"], "latest");
+    assert!(json.get("availableIn").is_none());
+
+    let output = which_output(&ws, &["--json", "mooncake"]);
+    assert_eq!(output.status.code(), Some(1));
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("should print JSON");
+    assert_eq!(json["found"], serde_json::json!(false));
+    assert_eq!(json["program"], serde_json::Value::Null);
+    assert_eq!(json["availableIn"], serde_json::json!(["nightly"]));
+
+    let (stdout, _) = which(&ws, &["--json", "--all", "mooncake"]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).expect("should print JSON");
+    let found = json
+        .as_array()
+        .expect("should print an array")
+        .iter()
+        .map(|r| (r["toolchain"].as_str(), r["found"].as_bool()))
+        .collect::<Vec<_>>();
+    assert!(found.contains(&(Some("latest"), Some(false))), "{json}");
+    assert!(found.contains(&(Some("nightly"), Some(true))), "{json}");
+}
+
+#[test]
+fn test_which_all_unresolved_toolchain() {
+    let ws = TestWorkspace::new();
+    ws.fake_toolchain("latest", &["moon"], "");
+    fs::create_dir_all(ws.moonup_home().join("toolchains/broken"))
+        .expect("should create toolchain directory");
+
+    let (stdout, _) = which(&ws, &["--all", "moon"]);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(
+        lines.contains(&"broken: Toolchain 'broken' is not installed"),
+        "{stdout}"
+    );
+    assert!(lines.iter().any(|l| l.starts_with("latest: ")), "{stdout}");
+
+    let (stdout, _) = which(&ws, &["--json", "--all", "moon"]);
+    let json: serde_json::Value = serde_json::from_str(&stdout).expect("should print JSON");
+    let resolutions = json.as_array().expect("should print an array");
+    let broken = resolutions
+        .iter()
+        .find(|r| r["toolchain"] == "broken")
+        .expect("should list the broken toolchain");
+    assert_eq!(broken["found"], serde_json::json!(false));
+    assert_eq!(broken["error"], "Toolchain 'broken' is not installed");
+    let latest = resolutions
+        .iter()
+        .find(|r| r["toolchain"] == "latest")
+        .expect("should list the latest toolchain");
+    assert_eq!(latest["found"], serde_json::json!(true));
+    assert!(latest.get("error").is_none());
+}
@@ -1,6 +1,8 @@
 use assert_fs::prelude::*;
 use insta::assert_snapshot;
-use moonup::archive::{extract_tar_gz, extract_zip};
+use moonup::archive::{
+    ArchiveFormat, extract_tar_gz, extract_tar_xz, extract_tar_zst, extract_zip,
+};
 
 use crate::util;
 
@@ -45,3 +47,279 @@ async fn test_archive_tar_gz_extraction() {
     let sha256 = format!("{:x}", hash);
     assert_snapshot!(sha256, @"65f77ae8d172385a19157f338ca63f6cdb836e1fce82751c2ea8d7e5c7991823");
 }
+
+/// Build a tar.gz archive with raw entry names, bypassing the path checks of
+/// `tar::Builder`
+fn build_tar_gz(entries: &[(&str, tar::EntryType, &str)]) -> Vec<u8> {
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for (name, entry_type, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        let raw_name = &mut header.as_gnu_mut().unwrap().name;
+        raw_name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(*entry_type);
+        header.set_mode(0o755);
+        if entry_type.is_symlink() {
+            header.set_link_name(content).unwrap();
+            header.set_size(0);
+            header.set_cksum();
+            builder.append(&header, std::io::empty()).unwrap();
+        } else {
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
+    }
+
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+#[tokio::test]
+async fn test_archive_tar_gz_rejects_unsafe_entries() {
+    let tempdir = assert_fs::TempDir::new().unwrap();
+    let destination = tempdir.path().join("toolchain");
+
+    let tar_gz = build_tar_gz(&[
+        ("bin/moon", tar::EntryType::Regular, "moon"),
+        ("../evil.txt", tar::EntryType::Regular, "evil"),
+        ("lib/escape", tar::EntryType::Symlink, "../../outside"),
+        ("lib/inside", tar::EntryType::Symlink, "../bin/moon"),
+    ]);
+
+    let reader = tokio::io::BufReader::new(std::io::Cursor::new(tar_gz));
+    let err = extract_tar_gz(reader, &destination)
+        .await
+        .expect_err("should reject unsafe entries");
+
+    let msg = err.to_string();
+    assert!(msg.contains("refusing to extract 2 unsafe archive entries"));
+    assert!(msg.contains("../evil.txt: path contains `..`"));
+    assert!(msg.contains("lib/escape -> ../../outside: link points outside the destination"));
+    assert!(!tempdir.path().join("evil.txt").exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_archive_tar_gz_rejects_chained_symlinks() {
+    let tempdir = assert_fs::TempDir::new().unwrap();
+    let destination = tempdir.path().join("toolchain");
+
+    let tar_gz = build_tar_gz(&[
+        ("d", tar::EntryType::Symlink, "."),
+        ("d/e", tar::EntryType::Symlink, "../outside"),
+        ("f", tar::EntryType::Symlink, "d/../outside"),
+        ("g", tar::EntryType::Symlink, "d"),
+    ]);
+
+    let reader = tokio::io::BufReader::new(std::io::Cursor::new(tar_gz));
+    let err = extract_tar_gz(reader, &destination)
+        .await
+        .expect_err("should reject chained symlinks");
+
+    let msg = err.to_string();
+    assert!(msg.contains("refusing to extract 2 unsafe archive entries"));
+    assert!(msg.contains("d/e: path passes through a symlink"));
+    assert!(msg.contains("f -> d/../outside: link points outside the destination"));
+    assert!(!destination.join("e").exists());
+    assert!(!destination.join("f").exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_archive_tar_gz_preserves_symlinks_and_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tempdir = assert_fs::TempDir::new().unwrap();
+
+    let tar_gz = build_tar_gz(&[
+        ("bin/moon", tar::EntryType::Regular, "moon"),
+        ("lib/moon", tar::EntryType::Symlink, "../bin/moon"),
+    ]);
+
+    let reader = tokio::io::BufReader::new(std::io::Cursor::new(tar_gz));
+    extract_tar_gz(reader, tempdir.path())
+        .await
+        .expect("should extract tar.gz");
+
+    let mode = std::fs::metadata(tempdir.path().join("bin/moon"))
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o755);
+    let target = std::fs::read_link(tempdir.path().join("lib/moon")).unwrap();
+    assert_eq!(target, std::path::Path::new("../bin/moon"));
+}
+
+/// Build a zip archive, entries with a target are symlinks
+fn build_zip(entries: &[(&str, u32, Option<&str>)]) -> Vec<u8> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, mode, target) in entries {
+        let options = SimpleFileOptions::default().unix_permissions(*mode);
+        match target {
+            Some(target) => writer.add_symlink(*name, *target, options).unwrap(),
+            None => {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(name.as_bytes()).unwrap();
+            }
+        }
+    }
+
+    writer.finish().unwrap().into_inner()
+}
+
+#[tokio::test]
+async fn test_archive_zip_rejects_unsafe_entries() {
+    let tempdir = assert_fs::TempDir::new().unwrap();
+    let destination = tempdir.path().join("toolchain");
+
+    let zip = build_zip(&[
+        ("bin/moon.exe", 0o755, None),
+        ("../evil.txt", 0o644, None),
+        ("/absolute.txt", 0o644, None),
+        ("lib/escape", 0o777, Some("../../outside")),
+    ]);
+
+    let reader = tokio::io::BufReader::new(std::io::Cursor::new(zip));
+    let err = extract_zip(reader, &destination)
+        .await
+        .expect_err("should reject unsafe entries");
+
+    let msg = err.to_string();
+    assert!(msg.contains("refusing to extract 3 unsafe archive entries"));
+    assert!(msg.contains("../evil.txt: path contains `..`"));
+    assert!(msg.contains("/absolute.txt: absolute path"));
+    assert!(msg.contains("lib/escape -> ../../outside: link points outside the destination"));
+    assert!(!tempdir.path().join("evil.txt").exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_archive_zip_rejects_chained_symlinks() {
+    let tempdir = assert_fs::TempDir::new().unwrap();
+    let destination = tempdir.path().join("toolchain");
+
+    let zip = build_zip(&[
+        ("d", 0o777, Some(".")),
+        ("f", 0o777, Some("d/../outside")),
+        ("g", 0o777, Some("d")),
+    ]);
+
+    let reader = tokio::io::BufReader::new(std::io::Cursor::new(zip));
+    let err = extract_zip(reader, &destination)
+        .await
+        .expect_err("should reject chained symlinks");
+
+    let msg = err.to_string();
+    assert!(msg.contains("refusing to extract 1 unsafe archive entries"));
+    assert!(msg.contains("f -> d/../outside: link points outside the destination"));
+    assert!(!destination.join("f").exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_archive_zip_preserves_symlinks_and_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tempdir = assert_fs::TempDir::new().unwrap();
+
+    let zip = build_zip(&[
+        ("bin/moon", 0o755, None),
+        ("bin/README", 0o644, None),
+        ("lib/moon", 0o777, Some("../bin/moon")),
+    ]);
+
+    let reader = tokio::io::BufReader::new(std::io::Cursor::new(zip));
+    extract_zip(reader, tempdir.path())
+        .await
+        .expect("should extract zip");
+
+    let mode_of = |path: &str| {
+        std::fs::metadata(tempdir.path().join(path))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o777
+    };
+    assert_eq!(mode_of("bin/moon"), 0o755);
+    assert_eq!(mode_of("bin/README"), 0o644);
+    let target = std::fs::read_link(tempdir.path().join("lib/moon")).unwrap();
+    assert_eq!(target, std::path::Path::new("../bin/moon"));
+}
+
+/// Build an uncompressed tarball with a single `world.txt` file
+fn build_tar() -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(5);
+    header.set_mode(0o644);
+    builder
+        .append_data(&mut header, "world.txt", "hello".as_bytes())
+        .unwrap();
+    builder.into_inner().unwrap()
+}
+
+#[tokio::test]
+async fn test_archive_tar_xz_extraction() {
+    use std::io::Write;
+
+    let tempdir = assert_fs::TempDir::new().unwrap();
+
+    let mut writer =
+        lzma_rust2::XzWriter::new(Vec::new(), lzma_rust2::XzOptions::with_preset(6)).unwrap();
+    writer.write_all(&build_tar()).unwrap();
+    let tar_xz = writer.finish().unwrap();
+    assert_eq!(
+        ArchiveFormat::from_magic(&tar_xz),
+        Some(ArchiveFormat::TarXz)
+    );
+
+    let expected = rattler_digest::compute_bytes_digest::<rattler_digest::Sha256>(&tar_xz);
+    let reader = tokio::io::BufReader::new(std::io::Cursor::new(tar_xz));
+    let hash = extract_tar_xz(reader, tempdir.path())
+        .await
+        .expect("should extract tar.xz");
+
+    tempdir.child("world.txt").assert("hello");
+    assert_eq!(hash, expected);
+}
+
+#[tokio::test]
+async fn test_archive_tar_zst_extraction() {
+    let tempdir = assert_fs::TempDir::new().unwrap();
+
+    let tar_zst = zstd::encode_all(build_tar().as_slice(), 0).unwrap();
+    assert_eq!(
+        ArchiveFormat::from_magic(&tar_zst),
+        Some(ArchiveFormat::TarZst)
+    );
+
+    let expected = rattler_digest::compute_bytes_digest::<rattler_digest::Sha256>(&tar_zst);
+    let reader = tokio::io::BufReader::new(std::io::Cursor::new(tar_zst));
+    let hash = extract_tar_zst(reader, tempdir.path())
+        .await
+        .expect("should extract tar.zst");
+
+    tempdir.child("world.txt").assert("hello");
+    assert_eq!(hash, expected);
+}
+
+#[test]
+fn test_archive_format_detection() {
+    let zip = include_bytes!("../fixtures/archive/test.zip");
+    let tar_gz = include_bytes!("../fixtures/archive/test.tar.gz");
+
+    assert_eq!(ArchiveFormat::from_magic(zip), Some(ArchiveFormat::Zip));
+    assert_eq!(
+        ArchiveFormat::from_magic(tar_gz),
+        Some(ArchiveFormat::TarGz)
+    );
+    assert_eq!(ArchiveFormat::from_magic(b"not an archive"), None);
+
+    assert_eq!("tar.zst".parse(), Ok(ArchiveFormat::TarZst));
+    assert_eq!("TGZ".parse(), Ok(ArchiveFormat::TarGz));
+    assert!("rar".parse::<ArchiveFormat>().is_err());
+}
@@ -11,12 +11,17 @@ fn cli() -> Command {
 #[test]
 fn test_help() {
     assert_cmd_snapshot!(cli().arg("--help"));
+    assert_cmd_snapshot!("cache", cli().arg("cache").arg("--help"));
     assert_cmd_snapshot!("default", cli().arg("default").arg("--help"));
+    assert_cmd_snapshot!("doctor", cli().arg("doctor").arg("--help"));
     assert_cmd_snapshot!("install", cli().arg("install").arg("--help"));
     assert_cmd_snapshot!("pin", cli().arg("pin").arg("--help"));
+    assert_cmd_snapshot!("repair", cli().arg("repair").arg("--help"));
     assert_cmd_snapshot!("run", cli().arg("run").arg("--help"));
+    assert_cmd_snapshot!("shims", cli().arg("shims").arg("--help"));
     assert_cmd_snapshot!("show", cli().arg("show").arg("--help"));
     assert_cmd_snapshot!("update", cli().arg("update").arg("--help"));
+    assert_cmd_snapshot!("verify", cli().arg("verify").arg("--help"));
     assert_cmd_snapshot!("which", cli().arg("which").arg("--help"));
     assert_cmd_snapshot!("completions", cli().arg("completions").arg("--help"));
 }
@@ -1,7 +1,12 @@
 use moonup::{
     constant,
     dist_server::schema::{Component, Release},
-    toolchain::{ToolchainSpec, index::InstallRecipe, package::populate_install},
+    toolchain::{
+        InstalledToolchain, ToolchainSpec,
+        index::InstallRecipe,
+        manifest::Manifest,
+        package::{extract_components, populate_install},
+    },
 };
 
 #[test]
@@ -35,6 +40,7 @@ fn test_populate_install_redownloads_invalid_cache() {
             name: "toolchain".to_string(),
             file: archive_file.to_string(),
             sha256: expected_sha256.to_string(),
+            format: None,
         }],
     };
 
@@ -54,9 +60,9 @@ fn test_populate_install_redownloads_invalid_cache() {
 
                         let cache_file = moonup_home
                             .join("downloads")
-                            .join("latest")
-                            .join(version)
-                            .join(archive_file);
+                            .join("store")
+                            .join("sha256")
+                            .join(expected_sha256);
                         assert!(cache_file.exists(), "cache file should exist");
 
                         std::fs::write(&cache_file, b"corrupted")
@@ -80,3 +86,225 @@ fn test_populate_install_redownloads_invalid_cache() {
         },
     );
 }
+
+#[test]
+fn test_populate_install_shares_store_across_channels() {
+    let tempdir = assert_fs::TempDir::new().expect("should create tempdir");
+    let moonup_home = tempdir.path().join(".moonup");
+
+    let version = "0.1.20241231+ba15a9a4e";
+    let archive_file = "test.tar.gz";
+    let archive_data = include_bytes!("../fixtures/archive/test.tar.gz");
+    let expected_sha256 = "65f77ae8d172385a19157f338ca63f6cdb836e1fce82751c2ea8d7e5c7991823";
+
+    let mut server = mockito::Server::new();
+    let download_path = format!("/download/v{version}/{archive_file}");
+    let _mock_download = server
+        .mock("GET", download_path.as_str())
+        .with_body(archive_data.as_ref())
+        .expect(1)
+        .create();
+
+    let release = Release {
+        version: version.to_string(),
+        layout_version1: None,
+        bundle_source_dir: None,
+        date: None,
+        targets: None,
+    };
+    let components = vec![Component {
+        name: "toolchain".to_string(),
+        file: archive_file.to_string(),
+        sha256: expected_sha256.to_string(),
+        format: None,
+    }];
+
+    let recipes = [
+        ToolchainSpec::Version(version.to_string()),
+        ToolchainSpec::Latest,
+    ]
+    .map(|spec| InstallRecipe {
+        spec,
+        release: release.clone(),
+        components: components.clone(),
+    });
+
+    temp_env::with_var(
+        constant::ENVNAME_MOONUP_DIST_SERVER,
+        Some(server.url()),
+        || {
+            temp_env::with_var(
+                constant::ENVNAME_MOONUP_HOME,
+                Some(moonup_home.as_os_str()),
+                || {
+                    let rt = tokio::runtime::Runtime::new().expect("should create runtime");
+                    rt.block_on(async {
+                        for recipe in recipes.iter() {
+                            populate_install(recipe)
+                                .await
+                                .expect("install should succeed");
+                        }
+                    });
+                },
+            );
+        },
+    );
+
+    for dirname in [version, "latest"] {
+        let installed_file = moonup_home
+            .join("toolchains")
+            .join(dirname)
+            .join("world.txt");
+        assert!(installed_file.exists(), "{dirname} should be installed");
+    }
+
+    let latest = InstalledToolchain::from_path(&moonup_home.join("toolchains").join("latest"))
+        .expect("should read installed toolchain");
+    let receipt = latest.receipt.expect("should have an install receipt");
+    assert_eq!(latest.name, ToolchainSpec::Latest);
+    assert_eq!(latest.tag.as_deref(), Some(version));
+    assert_eq!(receipt.release, release);
+    assert_eq!(receipt.components.len(), 1);
+    assert_eq!(receipt.components[0].sha256, expected_sha256);
+    assert_eq!(receipt.moonup_version, env!("CARGO_PKG_VERSION"));
+}
+
+#[test]
+fn test_populate_install_records_manifest() {
+    let tempdir = assert_fs::TempDir::new().expect("should create tempdir");
+    let moonup_home = tempdir.path().join(".moonup");
+
+    let version = "0.1.20241231+ba15a9a4e";
+    let archive_file = "test.tar.gz";
+    let archive_data = include_bytes!("../fixtures/archive/test.tar.gz");
+    let expected_sha256 = "65f77ae8d172385a19157f338ca63f6cdb836e1fce82751c2ea8d7e5c7991823";
+
+    let mut server = mockito::Server::new();
+    let download_path = format!("/download/v{version}/{archive_file}");
+    let _mock_download = server
+        .mock("GET", download_path.as_str())
+        .with_body(archive_data.as_ref())
+        .expect(1)
+        .create();
+
+    let recipe = InstallRecipe {
+        spec: ToolchainSpec::Version(version.to_string()),
+        release: Release {
+            version: version.to_string(),
+            layout_version1: None,
+            bundle_source_dir: None,
+            date: None,
+            targets: None,
+        },
+        components: vec![Component {
+            name: "toolchain".to_string(),
+            file: archive_file.to_string(),
+            sha256: expected_sha256.to_string(),
+            format: None,
+        }],
+    };
+
+    let toolchain_dir = moonup_home.join("toolchains").join(version);
+
+    temp_env::with_var(
+        constant::ENVNAME_MOONUP_DIST_SERVER,
+        Some(server.url()),
+        || {
+            temp_env::with_var(
+                constant::ENVNAME_MOONUP_HOME,
+                Some(moonup_home.as_os_str()),
+                || {
+                    let rt = tokio::runtime::Runtime::new().expect("should create runtime");
+                    rt.block_on(async {
+                        populate_install(&recipe)
+                            .await
+                            .expect("install should succeed");
+
+                        let manifest = Manifest::read(&toolchain_dir)
+                            .expect("should read manifest")
+                            .expect("should have a manifest");
+                        assert_eq!(manifest.files.len(), 1);
+                        assert_eq!(manifest.files[0].path, "world.txt");
+                        assert_eq!(manifest.files[0].size, 0);
+                        assert_eq!(
+                            manifest.files[0].sha256,
+                            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+                        );
+
+                        let report = manifest
+                            .verify(&toolchain_dir)
+                            .expect("should verify toolchain");
+                        assert!(report.is_intact(), "fresh install should be intact");
+
+                        std::fs::write(toolchain_dir.join("world.txt"), b"tampered")
+                            .expect("should tamper file");
+                        std::fs::write(toolchain_dir.join("extra.txt"), b"extra")
+                            .expect("should add extra file");
+
+                        let report = manifest
+                            .verify(&toolchain_dir)
+                            .expect("should verify toolchain");
+                        assert_eq!(report.modified, vec!["world.txt"]);
+                        assert!(report.missing.is_empty());
+                        assert_eq!(report.extra, vec!["extra.txt"]);
+
+                        std::fs::remove_file(toolchain_dir.join("world.txt"))
+                            .expect("should remove file");
+
+                        let report = manifest
+                            .verify(&toolchain_dir)
+                            .expect("should verify toolchain");
+                        assert!(report.modified.is_empty());
+                        assert_eq!(report.missing, vec!["world.txt"]);
+
+                        extract_components(&recipe, &toolchain_dir)
+                            .await
+                            .expect("should re-extract components");
+
+                        let report = manifest
+                            .verify(&toolchain_dir)
+                            .expect("should verify toolchain");
+                        assert!(report.modified.is_empty());
+                        assert!(report.missing.is_empty());
+                        assert_eq!(report.extra, vec!["extra.txt"]);
+
+                        // A corrupted download leaves the toolchain untouched
+                        let blob = moonup_home
+                            .join("downloads/store/sha256")
+                            .join(expected_sha256);
+                        std::fs::write(&blob, corrupted_tar_gz()).expect("should corrupt download");
+                        let err = extract_components(&recipe, &toolchain_dir)
+                            .await
+                            .expect_err("should reject corrupted download");
+                        assert!(err.to_string().contains("Checksum mismatch"));
+
+                        let report = manifest
+                            .verify(&toolchain_dir)
+                            .expect("should verify toolchain");
+                        assert!(report.modified.is_empty());
+                        assert!(report.missing.is_empty());
+                        assert!(
+                            toolchain_dir
+                                .join(constant::TOOLCHAIN_RECEIPT_FILE)
+                                .is_file()
+                        );
+                        assert!(!moonup_home.join("tmp").join(version).exists());
+                    });
+                },
+            );
+        },
+    );
+}
+
+/// Build a tar.gz archive with a `world.txt` other than the fixture's
+fn corrupted_tar_gz() -> Vec<u8> {
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(7);
+    header.set_mode(0o644);
+    builder
+        .append_data(&mut header, "world.txt", "corrupt".as_bytes())
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap()
+}
@@ -34,6 +34,20 @@ fn test_resolve_toolchain() {
     );
 }
 
+#[test]
+fn test_parse_toolchain_file() {
+    let file = resolve::ToolchainFile::parse(
+        "# pinned for CI\n\n  0.1.20241231+ba15a9a4e  \nauto-install = never\n",
+    );
+    assert_eq!(file.toolchain.as_deref(), Some("0.1.20241231+ba15a9a4e"));
+    assert_eq!(file.option("auto-install"), Some("never"));
+    assert_eq!(file.option("unknown"), None);
+
+    let file = resolve::ToolchainFile::parse("auto-install = prompt\n");
+    assert_eq!(file.toolchain, None);
+    assert_eq!(file.option("auto-install"), Some("prompt"));
+}
+
 #[test]
 #[serial]
 fn test_resolve_file() {
@@ -100,6 +100,38 @@ impl TestWorkspace {
     pub fn project_path(&self) -> &Path {
         self.project_path.as_path()
     }
+
+    /// Install a fake toolchain whose executables run the given shell script
+    ///
+    /// Returns the `bin` directory of the toolchain.
+    #[allow(unused)]
+    pub fn fake_toolchain(&self, name: &str, bins: &[&str], script: &str) -> PathBuf {
+        let bin_dir = self.moonup_home.join("toolchains").join(name).join("bin");
+        for bin in bins {
+            let path = bin_dir.join(bin);
+            std::fs::create_dir_all(path.parent().unwrap()).expect("should create bin directory");
+            std::fs::write(&path, format!("#!/bin/sh\n{script}\n"))
+                .expect("should create executable");
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                    .expect("should mark executable");
+            }
+        }
+        bin_dir
+    }
+
+    /// Copy the shim as the given command into the test workspace
+    #[allow(unused)]
+    pub fn shim(&self, name: &str) -> PathBuf {
+        let shim = self
+            .tempdir
+            .path()
+            .join(format!("{name}{}", std::env::consts::EXE_SUFFIX));
+        std::fs::copy(insta_cmd::get_cargo_bin("moonup-shim"), &shim).expect("should copy shim");
+        shim
+    }
 }
 
 #[allow(unused_macros)]